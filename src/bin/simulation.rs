@@ -21,10 +21,123 @@ mod models;
 #[path = "../data_access/mod.rs"]
 mod data_access;
 
+use crate::models::admission::{OverflowPolicy, OVERFLOW_POLICY_NAMES};
+use crate::models::duty_cycle::{DutyCyclePolicy, DUTY_CYCLE_POLICY_NAMES};
 use crate::models::gateway::*;
+use crate::models::network::Network;
+use crate::models::propagation::{PathLossModel, PATH_LOSS_MODEL_NAMES};
+use crate::models::region::{Region, REGION_NAMES};
+use crate::models::retransmission::{Backoff, BACKOFF_NAMES};
+use crate::data_access::scenario::*;
 
-fn main() {
-    let gateway = Gateway::new(0);
+/// Get the value following a command line flag
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|value| value.as_str())
+}
 
-    gateway.simulation();
+/// Print an error about the scenario and exit
+fn exit_with_error(message: String) -> ! {
+    eprintln!("Invalid scenario: {}", message);
+    std::process::exit(1);
 }
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    let mut scenario = match arg_value(&args, "--config") {
+        Some(path) => load_scenario(path).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e))),
+        None => ScenarioConfig::default(),
+    };
+
+    // Command line flags override the scenario file
+    if args.contains(&String::from("--random")) {
+        scenario.traffic.generate = true;
+    }
+    if args.contains(&String::from("--real-time")) {
+        scenario.mode = SimulationMode::RealTime;
+    }
+    if args.contains(&String::from("--adr")) {
+        scenario.network.adr.enabled = true;
+    }
+    if args.contains(&String::from("--downlinks")) {
+        scenario.network.downlink.enabled = true;
+    }
+    if args.contains(&String::from("--class-b")) {
+        scenario.network.class_b.enabled = true;
+    }
+    if args.contains(&String::from("--class-c")) {
+        scenario.network.class_c.enabled = true;
+    }
+    if args.contains(&String::from("--confirmed")) {
+        scenario.network.confirmed.enabled = true;
+        scenario.network.downlink.enabled = true;
+    }
+    if let Some(path) = arg_value(&args, "--statistics") {
+        scenario.statistics_file = Some(path.to_string());
+    }
+    if let Some(path) = arg_value(&args, "--trace-csv") {
+        scenario.trace_csv = Some(path.to_string());
+    }
+    if let Some(path) = arg_value(&args, "--trace-jsonl") {
+        scenario.trace_jsonl = Some(path.to_string());
+    }
+    if let Some(seed) = arg_value(&args, "--seed") {
+        let seed = seed.parse().unwrap_or_else(|_| exit_with_error(format!("seed: {} is not an unsigned integer", seed)));
+        scenario.seed = Some(seed);
+    }
+    if let Some(scale) = arg_value(&args, "--time-scale") {
+        scenario.replay.time_scale = scale.parse().unwrap_or_else(|_| exit_with_error(format!("time_scale: {} is not a number", scale)));
+    }
+    if let Some(shift) = arg_value(&args, "--time-shift-ms") {
+        scenario.replay.time_shift_ms = shift.parse().unwrap_or_else(|_| exit_with_error(format!("time_shift_ms: {} is not a number", shift)));
+    }
+    if let Some(level) = arg_value(&args, "--log-level") {
+        scenario.log_level = level.parse().unwrap_or_else(|_| exit_with_error(format!("log_level: {} is not a log level", level)));
+    }
+    if let Some(name) = arg_value(&args, "--overflow-policy") {
+        scenario.gateway.overflow = OverflowPolicy::from_name(name).unwrap_or_else(|| {
+            exit_with_error(format!("gateway.overflow must be one of {:?}, got {}", OVERFLOW_POLICY_NAMES, name))
+        });
+    }
+    if let Some(retries) = arg_value(&args, "--max-retries") {
+        scenario.gateway.retransmission.max_retries = retries.parse().unwrap_or_else(|_| {
+            exit_with_error(format!("gateway.retransmission.max_retries: {} is not an unsigned integer", retries))
+        });
+    }
+    if let Some(name) = arg_value(&args, "--backoff") {
+        scenario.gateway.retransmission.backoff = Backoff::from_name(name).unwrap_or_else(|| {
+            exit_with_error(format!("gateway.retransmission.backoff must be one of {:?}, got {}", BACKOFF_NAMES, name))
+        });
+    }
+    if let Some(name) = arg_value(&args, "--region") {
+        scenario.region = Some(Region::from_name(name).unwrap_or_else(|| {
+            exit_with_error(format!("region must be one of {:?}, got {}", REGION_NAMES, name))
+        }));
+    }
+    if let Some(name) = arg_value(&args, "--duty-cycle") {
+        scenario.duty_cycle = DutyCyclePolicy::from_name(name).unwrap_or_else(|| {
+            exit_with_error(format!("duty_cycle must be one of {:?}, got {}", DUTY_CYCLE_POLICY_NAMES, name))
+        });
+    }
+    if let Some(name) = arg_value(&args, "--path-loss") {
+        scenario.network.propagation.path_loss = PathLossModel::from_name(name).unwrap_or_else(|| {
+            exit_with_error(format!("network.propagation.path_loss must be one of {:?}, got {}", PATH_LOSS_MODEL_NAMES, name))
+        });
+    }
+    if let Some(name) = arg_value(&args, "--strategy") {
+        scenario.gateway.strategy = name.to_string();
+    }
+    if let Some(name) = arg_value(&args, "--rejection-matrix") {
+        scenario.gateway.capture.rejection_matrix = RejectionMatrixConfig::Named(name.to_string());
+    }
+    if let Err(e) = scenario.validate() {
+        exit_with_error(e);
+    }
+
+    let network = Network::from_config(scenario).unwrap_or_else(|e| exit_with_error(e));
+
+    network.simulation();
+}
\ No newline at end of file
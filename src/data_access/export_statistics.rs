@@ -0,0 +1,72 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::statistics::NetworkReport;
+use log::info;
+
+use std::error::Error;
+use std::fs::File;
+
+/// Write the end-of-run statistics as JSON
+pub fn write_statistics(report: &NetworkReport, path: &str) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+    serde_json::to_writer_pretty(file, report)?;
+
+    info!("Wrote statistics to {}", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::interference::ReceptionOutcome;
+    use crate::models::statistics::Statistics;
+    use chrono::{Duration, Local};
+    use std::fs;
+
+    #[test]
+    fn statistics_file_is_the_report_as_json() {
+        let start = Local::now();
+        let at = |ms| start + Duration::milliseconds(ms);
+        let stats = Statistics::new(2);
+        for packet_id in 0..2 {
+            stats.arrival(packet_id, 1, at(0));
+            stats.reception(packet_id, at(50), ReceptionOutcome::Received);
+        }
+        stats.service_start(0, at(50));
+        stats.completion(0, 1, at(50), at(150));
+        stats.dropped(1, "tail-drop");
+        let gateways = vec![stats.report(1, start, at(200))];
+        let report = NetworkReport::new(7, 2, 0, 1, 0, gateways, std::time::Duration::from_millis(3));
+
+        let path = std::env::temp_dir().join(format!("lorasim-{}-statistics.json", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        write_statistics(&report, &path).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(contents, serde_json::to_string_pretty(&report).unwrap());
+
+        let written: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(written["seed"], 7);
+        assert_eq!(written["delivery_ratio"], 0.5);
+        let gateway = &written["gateways"][0];
+        assert_eq!(gateway["delivered"], 1);
+        assert_eq!(gateway["dropped"]["tail-drop"], 1);
+        assert_eq!(gateway["latency_ms"]["median"], 150.0);
+        assert_eq!(gateway["channel_delivered"], serde_json::json!([0, 1]));
+    }
+}
\ No newline at end of file
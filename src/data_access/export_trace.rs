@@ -0,0 +1,112 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::trace::TraceRecord;
+use log::info;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Write the trace as CSV with a header line
+pub fn write_trace_csv(records: &[TraceRecord], path: &str) -> Result<(), Box<dyn Error>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writeln!(writer, "time_ms,gateway,packet_id,event,channel,detail")?;
+    for record in records {
+        writeln!(
+            writer,
+            "{:.3},{},{},{},{},{}",
+            record.time_ms,
+            record.gateway,
+            record.packet_id,
+            record.event.name(),
+            record.channel.map(|c| c.to_string()).unwrap_or_default(),
+            record.detail.as_deref().unwrap_or_default(),
+        )?;
+    }
+    writer.flush()?;
+
+    info!("Wrote {} trace records to {}", records.len(), path);
+    Ok(())
+}
+
+/// Write the trace as JSON Lines, one record per line
+pub fn write_trace_jsonl(records: &[TraceRecord], path: &str) -> Result<(), Box<dyn Error>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    for record in records {
+        serde_json::to_writer(&mut writer, record)?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+
+    info!("Wrote {} trace records to {}", records.len(), path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::trace::{Trace, TraceEvent};
+    use chrono::{Duration, Local};
+    use std::fs;
+
+    /// Records of a packet received by gateway 2, served by its channel 1 and delivered
+    fn records() -> Vec<TraceRecord> {
+        let start = Local::now();
+        let trace = Trace::new(start).for_gateway(2);
+        trace.record(start, 3, TraceEvent::Arrival, None, None);
+        trace.record(start + Duration::microseconds(56_576), 3, TraceEvent::Reception, None, Some("received"));
+        trace.record(start + Duration::microseconds(56_576), 3, TraceEvent::ServiceStart, Some(1), None);
+        trace.record(start + Duration::microseconds(113_152), 3, TraceEvent::Outcome, None, Some("delivered"));
+        trace.records()
+    }
+
+    /// Writer of the records to a file
+    type Export = fn(&[TraceRecord], &str) -> Result<(), Box<dyn Error>>;
+
+    /// Contents of the file `write` exports the records to
+    fn exported(name: &str, write: Export) -> String {
+        let path = std::env::temp_dir().join(format!("lorasim-{}-{}", std::process::id(), name));
+        let path = path.to_string_lossy().into_owned();
+        write(&records(), &path).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        contents
+    }
+
+    #[test]
+    fn csv_trace_matches_the_golden_output() {
+        assert_eq!(exported("trace.csv", write_trace_csv), "\
+time_ms,gateway,packet_id,event,channel,detail
+0.000,2,3,arrival,,
+56.576,2,3,reception,,received
+56.576,2,3,service_start,1,
+113.152,2,3,outcome,,delivered
+");
+    }
+
+    #[test]
+    fn jsonl_trace_matches_the_golden_output() {
+        assert_eq!(exported("trace.jsonl", write_trace_jsonl), r#"{"time_ms":0.0,"gateway":2,"packet_id":3,"event":"arrival","channel":null,"detail":null}
+{"time_ms":56.576,"gateway":2,"packet_id":3,"event":"reception","channel":null,"detail":"received"}
+{"time_ms":56.576,"gateway":2,"packet_id":3,"event":"service_start","channel":1,"detail":null}
+{"time_ms":113.152,"gateway":2,"packet_id":3,"event":"outcome","channel":null,"detail":"delivered"}
+"#);
+    }
+}
\ No newline at end of file
@@ -14,68 +14,153 @@
  * You should have received a copy of the GNU General Public License
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
-
-use crate::models::packet::Packet;
+use crate::models::node::{Node, NodePopulation, NodeState, Position, RadioSettings};
+use crate::models::packet::{Packet, PacketStream};
+use crate::models::rng::{stream_rng, RngStream};
+use crate::data_access::scenario::ScenarioConfig;
+use chrono::Local;
 use log::{info, error};
 use rand::Rng;
-use chrono::Duration;
-use serde_json::json;
 
 use std::error::Error;
+use std::f64::consts::PI;
 use std::fs::File;
-use std::collections::VecDeque;
-
-/// Setup data for the simulation
-fn setup_data(packets: &VecDeque<Packet>) -> Result<(), Box<dyn Error>> {
-    let data_file_path = "data/packet.json";
-    let file = File::create(data_file_path)?;
-    
-    let json_packets: Vec<_> = packets.iter().map(|packet| {
-        json!({
-            "packet_id": packet.packet_id,
-            "arrival_time": packet.arrival_time,
-            "processing_time": packet.processing_time,
-        })
-    }).collect();
-    
-    serde_json::to_writer_pretty(file, &json_packets)?;
-    Ok(())
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Writer of the packet file, one packet per line as JSON Lines for a `.jsonl` file and as a JSON array otherwise
+struct PacketWriter {
+    writer: BufWriter<File>,        // Packet file
+    json_lines: bool,               // Whether the file is JSON Lines
+    count: u64,                     // Packets written so far
 }
 
-/// Generate random packets for the simulation
-pub fn generate_random_packet() -> Result<VecDeque<Packet>, Box<dyn Error>> {
+impl PacketWriter {
+    fn create(data_file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(data_file_path)?);
+        let json_lines = Path::new(data_file_path).extension().is_some_and(|e| e == "jsonl");
+        if !json_lines {
+            write!(writer, "[")?;
+        }
+        Ok(PacketWriter { writer, json_lines, count: 0 })
+    }
+
+    fn write(&mut self, packet: &Packet) -> Result<(), Box<dyn Error>> {
+        if !self.json_lines && self.count > 0 {
+            write!(self.writer, ",")?;
+        }
+        writeln!(self.writer)?;
+        serde_json::to_writer(&mut self.writer, packet)?;
+        self.count += 1;
+        Ok(())
+    }
 
-    let mut rng = rand::thread_rng();
-    let mut packets: Vec<Packet> = Vec::with_capacity(1000);
+    fn finish(mut self) -> Result<u64, Box<dyn Error>> {
+        if !self.json_lines {
+            write!(self.writer, "\n]")?;
+        }
+        writeln!(self.writer)?;
+        self.writer.flush()?;
+        Ok(self.count)
+    }
+}
+
+/// Generated packets, saved to the packet file as the simulation consumes them
+struct RecordedPackets {
+    population: NodePopulation,     // Nodes generating the packets
+    writer: Option<PacketWriter>,   // Packet file, dropped after a write error
+    path: String,                   // Path of the packet file
+}
 
-    info!("Start generating random packets");
+impl Iterator for RecordedPackets {
+    type Item = Packet;
 
-    for packet_id in 0..1000 {
-        // Generate a random number between 50 and 150 for processing time (milliseconds)
-        let processing_time_ms = rng.gen_range(50..=150);
-        let processing_time = Duration::milliseconds(processing_time_ms);
+    fn next(&mut self) -> Option<Packet> {
+        let packet = self.population.next();
 
-        // Use Packet::new to create a packet, automatically setting the arrival time
-        let packet = Packet::new(packet_id, processing_time);
+        let result = match (&packet, self.writer.take()) {
+            (Some(packet), Some(mut writer)) => writer.write(packet).map(|_| self.writer = Some(writer)),
+            (None, Some(writer)) => writer.finish().map(|count| info!("Saved {} packets to {}", count, self.path)),
+            (_, None) => Ok(()),
+        };
+        if let Err(e) = result {
+            error!("Failed to save packets to {}: {}", self.path, e);
+        }
 
-        packets.push(packet);
+        packet
     }
+}
 
-    // Sort by arrival time
-    packets.sort_by(|a, b| a.arrival_time.cmp(&b.arrival_time));
+/// Place the nodes of the scenario uniformly in a disc around the origin and draw their radio settings
+fn create_nodes(scenario: &ScenarioConfig, seed: u64) -> Vec<Node> {
+    let traffic = &scenario.traffic;
+    let mut rng = stream_rng(seed, RngStream::Placement);
 
-    // Convert to VecDeque
-    let packet_queue: VecDeque<Packet> = VecDeque::from(packets);
+    // Transmit on the gateway channels of the traffic bandwidth unless frequencies are given
+    let channels: Vec<u32> = if traffic.frequencies.is_empty() {
+        scenario.gateway_channels().iter().filter(|c| c.bandwidth == traffic.bandwidth).map(|c| c.frequency).collect()
+    } else {
+        traffic.frequencies.clone()
+    };
 
-    // Check the length of the packet queue
-    if packet_queue.len() > 1000 {
-        error!("Packet queue size exceeded 1000, current size: {}", packet_queue.len());
-        panic!("Packet queue size exceeded 1000");
-    }
+    // The packets are shared evenly among the nodes
+    let node_count = u64::from(traffic.node_count);
+    let packet_count = u64::from(traffic.packet_count);
+
+    (0..traffic.node_count).map(|id| {
+        let distance = traffic.area_radius_m * rng.gen::<f64>().sqrt();
+        let angle = 2.0 * PI * rng.gen::<f64>();
+        Node {
+            id,
+            position: Position { x: distance * angle.cos(), y: distance * angle.sin() },
+            traffic: traffic.arrival.clone(),
+            radio: RadioSettings {
+                spreading_factor: rng.gen_range(traffic.spreading_factor[0]..=traffic.spreading_factor[1]),
+                tx_power_dbm: traffic.tx_power_dbm,
+                bandwidth: traffic.bandwidth,
+                coding_rate: traffic.coding_rate,
+                channels: channels.clone(),
+            },
+            state: NodeState {
+                frame_counter: 0,
+                remaining: packet_count / node_count + u64::from(u64::from(id) < packet_count % node_count),
+                busy_until: None,
+            },
+        }
+    }).collect()
+}
+
+/// Save the nodes of the population
+fn setup_nodes(nodes: &[Node], node_file_path: &str) -> Result<(), Box<dyn Error>> {
+    let file = File::create(node_file_path)?;
+    serde_json::to_writer_pretty(BufWriter::new(file), nodes)?;
+    Ok(())
+}
+
+/// Generate random packets for the simulation from a population of nodes seeded with `seed`
+///
+/// The nodes are saved to the node file, the packets are drawn and saved to the packet file as the stream is consumed
+pub fn generate_random_packet(scenario: &ScenarioConfig, seed: u64) -> Result<PacketStream, Box<dyn Error>> {
+
+    let traffic = &scenario.traffic;
+
+    info!("Start generating random packets from {} nodes", traffic.node_count);
 
-    setup_data(&packet_queue)?;
+    let nodes = create_nodes(scenario, seed);
+    setup_nodes(&nodes, &scenario.node_file)?;
 
-    info!("Successfully generated {} packets\n", packet_queue.len());
+    let population = NodePopulation::new(
+        nodes,
+        Local::now(),
+        traffic.payload_length,
+        traffic.rssi,
+        stream_rng(seed, RngStream::Traffic),
+        stream_rng(seed, RngStream::Arrival),
+    );
 
-    Ok(packet_queue)
+    Ok(Box::new(RecordedPackets {
+        population,
+        writer: Some(PacketWriter::create(&scenario.packet_file)?),
+        path: scenario.packet_file.clone(),
+    }))
 }
\ No newline at end of file
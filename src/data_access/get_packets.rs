@@ -14,42 +14,214 @@
  * You should have received a copy of the GNU General Public License
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
-
-use crate::models::packet::Packet;
+use crate::models::node::Node;
+use crate::models::packet::{Packet, PacketStream};
+use crate::data_access::scenario::ReplayConfig;
+use chrono::{DateTime, Local};
 use log::error;
 use log::info;
 use serde_json::from_reader;
 
 use std::error::Error;
-use std::collections::VecDeque;
 use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
 use std::path::Path;
 
-/// Load data from file
-pub fn load_data() -> Result<VecDeque<Packet>, Box<dyn Error>> {
+/// Packets read one per line from a JSON Lines file already checked by `check_json_lines`
+struct JsonLinesReader {
+    lines: Lines<BufReader<File>>,  // Lines of the packet file
+    replay: ReplayConfig,           // Transformation of the arrival times
+    trace_start: DateTime<Local>,   // Arrival time of the first packet of the file
+}
+
+impl JsonLinesReader {
+    /// Read the next stored packet, skipping blank lines
+    fn read_packet(lines: &mut Lines<BufReader<File>>) -> Option<Packet> {
+        for line in lines.by_ref() {
+            let line = line.unwrap_or_else(|e| panic!("Failed to read packet file: {}", e));
+            if line.trim().is_empty() {
+                continue;
+            }
+            let packet = serde_json::from_str(&line).unwrap_or_else(|e| {
+                error!("Invalid packet in packet file: {}", e);
+                panic!("Invalid packet in packet file: {}", e)
+            });
+            return Some(packet);
+        }
+        None
+    }
+}
+
+impl Iterator for JsonLinesReader {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        let mut packet = JsonLinesReader::read_packet(&mut self.lines)?;
+        packet.arrival_time = self.replay.arrival_time(self.trace_start, packet.arrival_time);
+        Some(packet)
+    }
+}
+
+/// Check every line of a JSON Lines packet file in one pass without keeping the packets
+///
+/// Each packet must parse, have valid PHY parameters and arrive no earlier than the packet before it
+fn check_json_lines(file: File) -> Result<usize, String> {
+    let mut count = 0;
+    let mut last_arrival: Option<DateTime<Local>> = None;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.map_err(|e| format!("line {}: {}", line_number, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let packet: Packet = serde_json::from_str(&line).map_err(|e| format!("line {}: invalid packet: {}", line_number, e))?;
+        packet.phy.validate().map_err(|e| format!("line {}: packet {}: {}", line_number, packet.packet_id, e))?;
+        if last_arrival.is_some_and(|last| packet.arrival_time < last) {
+            return Err(format!("line {}: packet {} arrives before the packet preceding it, \
+                the packets of a JSON Lines file must be sorted by arrival time", line_number, packet.packet_id));
+        }
+        last_arrival = Some(packet.arrival_time);
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Load data from file, returns the start of the trace and the packets in order of their replayed arrival time
+///
+/// A `.jsonl` file is checked in a first pass, then read as the simulation goes and must be sorted,
+/// any other file is a JSON array read into memory at once and sorted, which a large workload should avoid.
+/// Invalid packets are reported with their line or index
+pub fn load_data(data_file_path: &str, replay: &ReplayConfig) -> Result<(DateTime<Local>, PacketStream), Box<dyn Error>> {
 
-    let data_file_path = "data/packet.json";
     info!("Start loading data from file: {}", data_file_path);
 
     if !Path::new(data_file_path).exists() {
-        error!("Data file not found: {}", data_file_path);
-        panic!("Data file not found: {}", data_file_path);
+        return Err(format!("Data file not found: {}", data_file_path).into());
     }
 
     let file = File::open(data_file_path)?;
-    let tmp: Vec<Packet> = from_reader(file)?;
 
-    let packets: Vec<Packet> = tmp.iter().map(|packet| {
-        Packet::new(packet.packet_id, packet.processing_time)
-    }).collect();
+    if Path::new(data_file_path).extension().is_some_and(|e| e == "jsonl") {
+        let count = check_json_lines(file).map_err(|e| format!("{} {}", data_file_path, e))?;
+        let file = File::open(data_file_path)?;
+        let mut lines = BufReader::new(file).lines();
+        let mut first = match JsonLinesReader::read_packet(&mut lines) {
+            Some(packet) => packet,
+            None => return Ok((Local::now(), Box::new(std::iter::empty()))),
+        };
+        let trace_start = first.arrival_time;
+        first.arrival_time = replay.arrival_time(trace_start, trace_start);
+        let reader = JsonLinesReader { lines, replay: replay.clone(), trace_start };
 
-    // Check the length of the packet queue
-    if packets.len() > 1000 {
-        error!("Packet queue size exceeded 1000, current size: {}", packets.len());
-        panic!("Packet queue size exceeded 1000");
+        info!("Streaming {} packets from {}", count, data_file_path);
+        return Ok((trace_start, Box::new(std::iter::once(first).chain(reader))));
     }
 
+    let mut packets: Vec<Packet> = from_reader(BufReader::new(file))?;
+    for (index, packet) in packets.iter().enumerate() {
+        packet.phy.validate().map_err(|e| format!("{} packet at index {} (ID {}): {}", data_file_path, index, packet.packet_id, e))?;
+    }
+
+    // The trace starts at its earliest arrival, the offsets from it are rescaled and shifted
+    let trace_start = packets.iter().map(|p| p.arrival_time).min().unwrap_or_else(Local::now);
+    for packet in &mut packets {
+        packet.arrival_time = replay.arrival_time(trace_start, packet.arrival_time);
+    }
+    packets.sort_by_key(|p| p.arrival_time);
+
     info!("Successfully loaded {} packets\n", packets.len());
 
-    Ok(VecDeque::from(packets))
+    Ok((trace_start, Box::new(packets.into_iter())))
+}
+
+/// Read the nodes saved with generated packets
+pub fn load_nodes(node_file_path: &str) -> Result<Vec<Node>, Box<dyn Error>> {
+    let file = File::open(node_file_path)?;
+    let nodes: Vec<Node> = from_reader(BufReader::new(file))?;
+
+    info!("Loaded {} nodes from {}", nodes.len(), node_file_path);
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::phy::PhyParams;
+    use chrono::Duration;
+    use std::fs;
+
+    /// Packet `id` arriving `offset_ms` after `start` with the spreading factor `sf`
+    fn packet(id: u32, start: DateTime<Local>, offset_ms: i64, sf: u8) -> Packet {
+        Packet::from_phy(id, PhyParams { spreading_factor: sf, ..PhyParams::default() }, start + Duration::milliseconds(offset_ms))
+    }
+
+    /// Load `contents` written to a file called `name`
+    fn load(name: &str, contents: &str) -> Result<Vec<Packet>, String> {
+        let path = std::env::temp_dir().join(format!("lorasim-{}-{}", std::process::id(), name));
+        let path = path.to_string_lossy().into_owned();
+        fs::write(&path, contents).unwrap();
+        let loaded = load_data(&path, &ReplayConfig::default()).map(|(_, packets)| packets.collect());
+        fs::remove_file(&path).unwrap();
+        loaded.map_err(|e| e.to_string())
+    }
+
+    /// One JSON line per packet
+    fn json_lines(packets: &[Packet]) -> String {
+        packets.iter().map(|p| serde_json::to_string(p).unwrap() + "\n").collect()
+    }
+
+    #[test]
+    fn sorted_json_lines_are_streamed_in_order() {
+        let start = Local::now();
+        let packets = [packet(1, start, 0, 7), packet(2, start, 10, 9), packet(3, start, 10, 12)];
+        let loaded = load("sorted.jsonl", &json_lines(&packets)).unwrap();
+        assert_eq!(loaded.iter().map(|p| p.packet_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn out_of_order_json_lines_are_rejected_with_the_line_number() {
+        let start = Local::now();
+        let packets = [packet(1, start, 0, 7), packet(2, start, 20, 7), packet(3, start, 10, 7)];
+        let error = load("unsorted.jsonl", &json_lines(&packets)).unwrap_err();
+        assert!(error.contains("line 3: packet 3 arrives before"), "{}", error);
+    }
+
+    #[test]
+    fn invalid_phy_parameters_are_rejected_with_the_line_number() {
+        let start = Local::now();
+        let mut zero_bandwidth = packet(3, start, 20, 7);
+        zero_bandwidth.phy.bandwidth = 0;
+        let mut bad_coding_rate = packet(3, start, 20, 7);
+        bad_coding_rate.phy.coding_rate = 5;
+        let mut bad_spreading_factor = packet(2, start, 10, 7);
+        bad_spreading_factor.phy.spreading_factor = 40;
+
+        let error = load("sf.jsonl", &json_lines(&[packet(1, start, 0, 7), bad_spreading_factor])).unwrap_err();
+        assert!(error.contains("line 2: packet 2: spreading_factor must be within 7 to 12, got 40"), "{}", error);
+        let error = load("bandwidth.jsonl", &json_lines(&[packet(1, start, 0, 7), packet(2, start, 10, 7), zero_bandwidth])).unwrap_err();
+        assert!(error.contains("line 3: packet 3: bandwidth must be"), "{}", error);
+        let error = load("coding_rate.jsonl", &json_lines(&[bad_coding_rate])).unwrap_err();
+        assert!(error.contains("line 1: packet 3: coding_rate must be within 1 to 4, got 5"), "{}", error);
+    }
+
+    #[test]
+    fn invalid_json_lines_are_rejected_with_the_line_number() {
+        let start = Local::now();
+        let contents = json_lines(&[packet(1, start, 0, 7)]) + "\n{not a packet}\n";
+        let error = load("broken.jsonl", &contents).unwrap_err();
+        assert!(error.contains("line 3: invalid packet"), "{}", error);
+    }
+
+    #[test]
+    fn json_array_packets_are_sorted_and_checked() {
+        let start = Local::now();
+        let packets = vec![packet(1, start, 20, 7), packet(2, start, 0, 8)];
+        let loaded = load("sorted.json", &serde_json::to_string(&packets).unwrap()).unwrap();
+        assert_eq!(loaded.iter().map(|p| p.packet_id).collect::<Vec<_>>(), vec![2, 1]);
+
+        let mut packets = vec![packet(1, start, 0, 7), packet(2, start, 10, 7)];
+        packets[1].phy.spreading_factor = 6;
+        let error = load("invalid.json", &serde_json::to_string(&packets).unwrap()).unwrap_err();
+        assert!(error.contains("packet at index 1 (ID 2): spreading_factor must be within 7 to 12, got 6"), "{}", error);
+    }
 }
\ No newline at end of file
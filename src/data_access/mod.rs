@@ -17,4 +17,7 @@
 
 pub mod generate_packets;
 pub mod get_packets;
-pub mod setup_logger;
\ No newline at end of file
+pub mod setup_logger;
+pub mod scenario;
+pub mod export_trace;
+pub mod export_statistics;
\ No newline at end of file
@@ -0,0 +1,468 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::admission::OverflowPolicy;
+use crate::models::adr::AdrConfig;
+use crate::models::arrival::ArrivalProcess;
+use crate::models::retransmission::RetransmissionConfig;
+use crate::models::channel::ChannelConfig;
+use crate::models::class_b::ClassBConfig;
+use crate::models::class_c::ClassCConfig;
+use crate::models::confirmed::ConfirmedConfig;
+use crate::models::downlink::DownlinkConfig;
+use crate::models::duty_cycle::DutyCyclePolicy;
+use crate::models::gateway::SimulationMode;
+use crate::models::interference::{RejectionMatrix, REJECTION_MATRIX_NAMES};
+use crate::models::network::GatewaySite;
+use crate::models::node::Position;
+use crate::models::propagation::Propagation;
+use crate::models::region::{Region, SubBand};
+use crate::models::strategy::STRATEGY_NAMES;
+use chrono::{DateTime, Duration, Local};
+use log::LevelFilter;
+use serde::{Serialize, Deserialize};
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Scenario of a simulation run, loaded from a TOML or JSON file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScenarioConfig {
+    pub mode: SimulationMode,       // How the simulation advances time
+    pub seed: Option<u64>,          // Master seed of the random streams, drawn from entropy when absent
+    pub region: Option<Region>,     // Regional parameters the channels, data rates and power must follow, none when absent
+    pub duty_cycle: DutyCyclePolicy, // What the nodes do with uplinks the duty cycle of the region forbids
+    pub packet_file: String,        // File the packets are saved to and loaded from
+    pub node_file: String,          // File the generated nodes are saved to
+    pub log_file: String,           // File the simulation log is written to
+    pub log_level: LevelFilter,     // Most verbose level written to the log, lower it for large runs
+    pub statistics_file: Option<String>, // JSON file the end-of-run statistics are written to
+    pub trace_csv: Option<String>,  // CSV file the packet lifecycle trace is written to
+    pub trace_jsonl: Option<String>, // JSON Lines file the packet lifecycle trace is written to
+    pub traffic: TrafficConfig,     // Generated traffic
+    pub replay: ReplayConfig,       // Timing of the packets loaded from the packet file
+    pub gateway: GatewayConfig,     // Gateway queues and reception
+    pub network: NetworkConfig,     // Gateways of the network and their coverage
+}
+
+/// Parameters of the generated packets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrafficConfig {
+    pub generate: bool,             // Generate new packets instead of loading the packet file
+    pub packet_count: u32,          // Number of packets to generate, shared evenly among the nodes
+    pub node_count: u32,            // Number of nodes sending the packets
+    pub area_radius_m: f64,         // Radius of the disc around the origin the nodes are placed in
+    pub tx_power_dbm: f64,          // Transmission power of the nodes
+    pub spreading_factor: [u8; 2],  // Inclusive range of spreading factors, one is drawn per node
+    pub payload_length: [u8; 2],    // Inclusive range of payload lengths in bytes
+    pub rssi: [f64; 2],             // Inclusive range of received power in dBm
+    pub bandwidth: u32,             // Bandwidth in Hz
+    pub coding_rate: u8,            // Coding rate 4/(4 + coding_rate)
+    pub frequencies: Vec<u32>,      // Channel plan of the nodes in Hz, one is drawn per packet, the gateway channels when empty
+    pub arrival: ArrivalProcess,    // Process generating the arrival times of each node
+}
+
+/// Transformation of the arrival times of a loaded trace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReplayConfig {
+    pub time_scale: f64,            // Factor applied to the arrival offsets from the start of the trace
+    pub time_shift_ms: f64,         // Delay added to every arrival after scaling
+}
+
+/// Parameters of the gateway
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GatewayConfig {
+    pub id: u32,                    // Gateway ID
+    pub channels: Vec<ChannelConfig>, // Channels with their frequency, bandwidth and queue capacity, those of the region when empty
+    pub pending_queue_capacity: usize, // Capacity of the pending queue
+    pub overflow: OverflowPolicy,   // Behaviour of the pending queue when it overflows
+    pub retransmission: RetransmissionConfig, // Retry limit and backoff of timed-out packets
+    pub time_threshold_ms: i64,     // Time threshold for packet timeout in milliseconds
+    pub strategy: String,           // Name of the allocation strategy
+    pub capture: CaptureConfig,     // Capture effect of concurrent transmissions
+}
+
+/// Gateways of the network, each with the queues and receiver of the gateway section
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    pub gateways: Vec<GatewaySite>, // Gateways with their ID and position, a single gateway receiving at the stored RSSI when empty
+    pub propagation: Propagation,   // Path loss, shadowing and sensitivity deciding which gateways hear each node
+    pub adr: AdrConfig,             // Adaptive data rate of the network server
+    pub downlink: DownlinkConfig,   // Class A downlinks answering the uplinks
+    pub confirmed: ConfirmedConfig, // Confirmed uplinks acknowledged by a downlink
+    pub class_b: ClassBConfig,      // Beacons and ping slots of Class B nodes
+    pub class_c: ClassCConfig,      // Continuous reception and multicast of Class C nodes
+}
+
+/// Parameters of the capture effect
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    pub threshold_db: f64,          // Minimum SIR against the same SF, overrides the diagonal of the rejection matrix
+    pub lock_symbols: u16,          // Preamble symbols the receiver needs to lock onto a packet
+    pub rejection_matrix: RejectionMatrixConfig, // Built-in matrix name or custom 6x6 matrix
+}
+
+/// Inter-SF rejection matrix given by name or by value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RejectionMatrixConfig {
+    Named(String),
+    Custom(Box<[[f64; 6]; 6]>),
+}
+
+impl Default for ScenarioConfig {
+    fn default() -> Self {
+        ScenarioConfig {
+            mode: SimulationMode::EventDriven,
+            seed: None,
+            region: None,
+            duty_cycle: DutyCyclePolicy::default(),
+            packet_file: String::from("data/packet.json"),
+            node_file: String::from("data/nodes.json"),
+            log_file: String::from("logs/simulation.log"),
+            log_level: LevelFilter::Info,
+            statistics_file: Some(String::from("data/statistics.json")),
+            trace_csv: None,
+            trace_jsonl: None,
+            traffic: TrafficConfig::default(),
+            replay: ReplayConfig::default(),
+            gateway: GatewayConfig::default(),
+            network: NetworkConfig::default(),
+        }
+    }
+}
+
+impl Default for TrafficConfig {
+    fn default() -> Self {
+        TrafficConfig {
+            generate: false,
+            packet_count: 1000,
+            node_count: 1,
+            area_radius_m: 1000.0,
+            tx_power_dbm: 14.0,
+            spreading_factor: [7, 12],
+            payload_length: [10, 50],
+            rssi: [-120.0, -60.0],
+            bandwidth: 125_000,
+            coding_rate: 1,
+            frequencies: Vec::new(),
+            arrival: ArrivalProcess::default(),
+        }
+    }
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        ReplayConfig {
+            time_scale: 1.0,
+            time_shift_ms: 0.0,
+        }
+    }
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        GatewayConfig {
+            id: 0,
+            channels: Vec::new(),
+            pending_queue_capacity: 1000,
+            overflow: OverflowPolicy::default(),
+            retransmission: RetransmissionConfig::default(),
+            time_threshold_ms: 10_000,
+            strategy: String::from("sjf-time-load"),
+            capture: CaptureConfig::default(),
+        }
+    }
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig {
+            threshold_db: 6.0,
+            lock_symbols: 5,
+            rejection_matrix: RejectionMatrixConfig::Named(String::from("croce")),
+        }
+    }
+}
+
+impl RejectionMatrixConfig {
+    /// Resolve the matrix, `None` for perfectly orthogonal spreading factors
+    pub fn resolve(&self) -> Option<RejectionMatrix> {
+        match self {
+            RejectionMatrixConfig::Named(name) => RejectionMatrix::from_name(name).flatten(),
+            RejectionMatrixConfig::Custom(matrix) => Some(RejectionMatrix(**matrix)),
+        }
+    }
+}
+
+impl ReplayConfig {
+    /// Replayed arrival time of a packet recorded at `arrival_time` in a trace starting at `trace_start`
+    pub fn arrival_time(&self, trace_start: DateTime<Local>, arrival_time: DateTime<Local>) -> DateTime<Local> {
+        let offset_us = arrival_time.signed_duration_since(trace_start).num_microseconds().unwrap_or(i64::MAX) as f64;
+        trace_start + Duration::microseconds((offset_us * self.time_scale + self.time_shift_ms * 1000.0).round() as i64)
+    }
+}
+
+impl ScenarioConfig {
+    /// Channels of the gateways, the default channels of the region, or of EU868 without region, when none are given
+    pub fn gateway_channels(&self) -> Vec<ChannelConfig> {
+        if self.gateway.channels.is_empty() {
+            self.region.unwrap_or(Region::Eu868).default_channels()
+        } else {
+            self.gateway.channels.clone()
+        }
+    }
+
+    /// Sub-bands whose duty cycle the nodes are held to, none without region or when the scenario turns it off
+    pub fn duty_cycle_sub_bands(&self) -> &'static [SubBand] {
+        match self.region {
+            Some(region) if self.duty_cycle != DutyCyclePolicy::Off => region.sub_bands(),
+            _ => &[],
+        }
+    }
+
+    /// Gateways to simulate, the single gateway `gateway.id` at the origin when the network lists none
+    pub fn gateway_sites(&self) -> Vec<GatewaySite> {
+        if self.network.gateways.is_empty() {
+            vec![GatewaySite { id: self.gateway.id, position: Position::default() }]
+        } else {
+            self.network.gateways.clone()
+        }
+    }
+
+    /// Check that every value is usable, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        let traffic = &self.traffic;
+        let gateway = &self.gateway;
+
+        if self.packet_file.is_empty() {
+            return Err(String::from("packet_file must not be empty"));
+        }
+        if traffic.generate && self.node_file.is_empty() {
+            return Err(String::from("node_file must not be empty"));
+        }
+        if self.log_file.is_empty() {
+            return Err(String::from("log_file must not be empty"));
+        }
+        if traffic.packet_count == 0 {
+            return Err(String::from("traffic.packet_count must be at least 1"));
+        }
+        if traffic.node_count == 0 {
+            return Err(String::from("traffic.node_count must be at least 1"));
+        }
+        if !traffic.area_radius_m.is_finite() || traffic.area_radius_m < 0.0 {
+            return Err(format!("traffic.area_radius_m must not be negative, got {}", traffic.area_radius_m));
+        }
+        if !traffic.tx_power_dbm.is_finite() {
+            return Err(String::from("traffic.tx_power_dbm must be a finite number"));
+        }
+        let [sf_min, sf_max] = traffic.spreading_factor;
+        if sf_min > sf_max || sf_min < 7 || sf_max > 12 {
+            return Err(format!("traffic.spreading_factor must be an increasing range within 7 to 12, got {:?}", traffic.spreading_factor));
+        }
+        if traffic.payload_length[0] > traffic.payload_length[1] {
+            return Err(format!("traffic.payload_length must be an increasing range, got {:?}", traffic.payload_length));
+        }
+        if !traffic.rssi.iter().all(|r| r.is_finite()) || traffic.rssi[0] > traffic.rssi[1] {
+            return Err(format!("traffic.rssi must be an increasing range, got {:?}", traffic.rssi));
+        }
+        if ![125_000, 250_000, 500_000].contains(&traffic.bandwidth) {
+            return Err(format!("traffic.bandwidth must be 125000, 250000 or 500000 Hz, got {}", traffic.bandwidth));
+        }
+        if !(1..=4).contains(&traffic.coding_rate) {
+            return Err(format!("traffic.coding_rate must be within 1 to 4, got {}", traffic.coding_rate));
+        }
+        if !self.replay.time_scale.is_finite() || self.replay.time_scale <= 0.0 {
+            return Err(format!("replay.time_scale must be positive, got {}", self.replay.time_scale));
+        }
+        if !self.replay.time_shift_ms.is_finite() || self.replay.time_shift_ms < 0.0 {
+            return Err(format!("replay.time_shift_ms must not be negative, got {}", self.replay.time_shift_ms));
+        }
+        traffic.arrival.validate().map_err(|e| format!("traffic.arrival.{}", e))?;
+        let channels = self.gateway_channels();
+        if traffic.generate && !channels.iter().any(|c| c.bandwidth == traffic.bandwidth) {
+            return Err(format!("gateway.channels must include a channel with the traffic bandwidth of {} Hz", traffic.bandwidth));
+        }
+        for (i, channel) in channels.iter().enumerate() {
+            if channel.frequency == 0 {
+                return Err(format!("gateway.channels[{}].frequency must be positive", i));
+            }
+            if ![125_000, 250_000, 500_000].contains(&channel.bandwidth) {
+                return Err(format!("gateway.channels[{}].bandwidth must be 125000, 250000 or 500000 Hz, got {}", i, channel.bandwidth));
+            }
+            if channel.capacity == 0 {
+                return Err(format!("gateway.channels[{}].capacity must be at least 1", i));
+            }
+        }
+        if let Some(region) = self.region {
+            self.validate_region(region, &channels)?;
+        }
+        if gateway.pending_queue_capacity == 0 {
+            return Err(String::from("gateway.pending_queue_capacity must be at least 1"));
+        }
+        if let OverflowPolicy::Red(red) = &gateway.overflow {
+            if !(red.min_threshold >= 0.0 && red.min_threshold < red.max_threshold && red.max_threshold <= 1.0) {
+                return Err(format!("gateway.overflow thresholds must satisfy 0 <= min_threshold < max_threshold <= 1, got {} and {}", red.min_threshold, red.max_threshold));
+            }
+            if !(red.max_probability > 0.0 && red.max_probability <= 1.0) {
+                return Err(format!("gateway.overflow.max_probability must be within 0 (exclusive) to 1, got {}", red.max_probability));
+            }
+            if !(red.weight > 0.0 && red.weight <= 1.0) {
+                return Err(format!("gateway.overflow.weight must be within 0 (exclusive) to 1, got {}", red.weight));
+            }
+        }
+        gateway.retransmission.backoff.validate().map_err(|e| format!("gateway.retransmission.backoff.{}", e))?;
+        if gateway.time_threshold_ms <= 0 {
+            return Err(format!("gateway.time_threshold_ms must be positive, got {}", gateway.time_threshold_ms));
+        }
+        if !STRATEGY_NAMES.contains(&gateway.strategy.as_str()) {
+            return Err(format!("gateway.strategy must be one of {:?}, got {}", STRATEGY_NAMES, gateway.strategy));
+        }
+        if !gateway.capture.threshold_db.is_finite() {
+            return Err(String::from("gateway.capture.threshold_db must be a finite number"));
+        }
+        match &gateway.capture.rejection_matrix {
+            RejectionMatrixConfig::Named(name) => {
+                if !REJECTION_MATRIX_NAMES.contains(&name.as_str()) {
+                    return Err(format!("gateway.capture.rejection_matrix must be one of {:?} or a 6x6 matrix, got {}", REJECTION_MATRIX_NAMES, name));
+                }
+            }
+            RejectionMatrixConfig::Custom(matrix) => {
+                for (i, row) in matrix.iter().enumerate() {
+                    if let Some(j) = row.iter().position(|value| !value.is_finite()) {
+                        return Err(format!("gateway.capture.rejection_matrix[{}][{}] must be a finite number, got {}", i, j, row[j]));
+                    }
+                }
+            }
+        }
+        for (i, site) in self.network.gateways.iter().enumerate() {
+            if !site.position.x.is_finite() || !site.position.y.is_finite() {
+                return Err(format!("network.gateways[{}].position must be finite", i));
+            }
+            if self.network.gateways[..i].iter().any(|other| other.id == site.id) {
+                return Err(format!("network.gateways[{}].id {} is already used", i, site.id));
+            }
+        }
+        if self.mode == SimulationMode::RealTime && !self.network.gateways.is_empty() {
+            return Err(String::from("network.gateways requires the event-driven mode, the real-time mode simulates a single gateway"));
+        }
+        self.network.propagation.validate().map_err(|e| format!("network.propagation.{}", e))?;
+        self.network.adr.validate().map_err(|e| format!("network.adr.{}", e))?;
+        self.network.downlink.validate().map_err(|e| format!("network.downlink.{}", e))?;
+        if self.mode == SimulationMode::RealTime && self.network.downlink.enabled {
+            return Err(String::from("network.downlink requires the event-driven mode"));
+        }
+        self.network.confirmed.validate().map_err(|e| format!("network.confirmed.{}", e))?;
+        if self.network.confirmed.enabled && !self.network.downlink.enabled {
+            return Err(String::from("network.confirmed requires network.downlink, the acknowledgements are downlinks"));
+        }
+        self.network.class_b.validate().map_err(|e| format!("network.class_b.{}", e))?;
+        if self.mode == SimulationMode::RealTime && self.network.class_b.enabled {
+            return Err(String::from("network.class_b requires the event-driven mode"));
+        }
+        self.network.class_c.validate().map_err(|e| format!("network.class_c.{}", e))?;
+        if self.mode == SimulationMode::RealTime && self.network.class_c.enabled {
+            return Err(String::from("network.class_c requires the event-driven mode"));
+        }
+        if self.network.class_c.enabled && self.network.class_b.enabled {
+            return Err(String::from("network.class_c and network.class_b are exclusive, a node is in one class at a time"));
+        }
+        if self.network.class_c.multicast.fragments > 0 && !self.network.class_c.enabled {
+            return Err(String::from("network.class_c.multicast requires network.class_c.enabled, only Class C nodes hear the fragments"));
+        }
+
+        Ok(())
+    }
+
+    /// Check that the channels, data rates and power of the scenario are allowed in the region
+    fn validate_region(&self, region: Region, channels: &[ChannelConfig]) -> Result<(), String> {
+        let traffic = &self.traffic;
+        let name = region.name();
+
+        for (i, channel) in channels.iter().enumerate() {
+            if !region.is_uplink_channel(channel.frequency, channel.bandwidth) {
+                return Err(format!("gateway.channels[{}] at {} Hz with {} Hz bandwidth is not an uplink channel of {}",
+                    i, channel.frequency, channel.bandwidth, name));
+            }
+        }
+        for &frequency in &traffic.frequencies {
+            if !region.is_uplink_channel(frequency, traffic.bandwidth) {
+                return Err(format!("traffic.frequencies: {} Hz with {} Hz bandwidth is not an uplink channel of {}",
+                    frequency, traffic.bandwidth, name));
+            }
+        }
+        for sf in traffic.spreading_factor[0]..=traffic.spreading_factor[1] {
+            if !region.allows(sf, traffic.bandwidth) {
+                return Err(format!("traffic.spreading_factor: SF{} with {} Hz bandwidth is not a data rate of {}",
+                    sf, traffic.bandwidth, name));
+            }
+        }
+        if traffic.tx_power_dbm > region.max_eirp_dbm() {
+            return Err(format!("traffic.tx_power_dbm must be at most the {} dBm allowed in {}, got {}",
+                region.max_eirp_dbm(), name, traffic.tx_power_dbm));
+        }
+        Ok(())
+    }
+}
+
+/// Load a scenario from a `.toml` or `.json` file and validate it
+pub fn load_scenario(path: &str) -> Result<ScenarioConfig, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+
+    let scenario: ScenarioConfig = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&content)?,
+        Some("json") => serde_json::from_str(&content)?,
+        _ => return Err(format!("unsupported scenario file {}, expected a .toml or .json extension", path).into()),
+    };
+
+    scenario.validate()?;
+
+    Ok(scenario)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_matrix(matrix: [[f64; 6]; 6]) -> ScenarioConfig {
+        let mut scenario = ScenarioConfig::default();
+        scenario.gateway.capture.rejection_matrix = RejectionMatrixConfig::Custom(Box::new(matrix));
+        scenario
+    }
+
+    #[test]
+    fn custom_rejection_matrix_is_accepted() {
+        assert!(with_matrix(RejectionMatrix::goursaud().0).validate().is_ok());
+    }
+
+    #[test]
+    fn custom_rejection_matrix_rejects_non_finite_entries() {
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut matrix = RejectionMatrix::croce().0;
+            matrix[2][4] = value;
+            let error = with_matrix(matrix).validate().unwrap_err();
+            assert!(error.starts_with("gateway.capture.rejection_matrix[2][4]"), "{}", error);
+        }
+    }
+}
\ No newline at end of file
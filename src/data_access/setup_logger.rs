@@ -22,10 +22,9 @@ use std::path::Path;
 
 static LOGGER: OnceCell<()> = OnceCell::new();
 
-/// Setup the logger for the application
-pub fn setup_logger() -> Result<(), fern::InitError> {
+/// Setup the logger for the application, writing records up to `level`
+pub fn setup_logger(log_file_path: &str, level: log::LevelFilter) -> Result<(), fern::InitError> {
     LOGGER.get_or_try_init(|| {
-        let log_file_path = "logs/simulation.log";
         
         if Path::new(log_file_path).exists() {
             let mut file = OpenOptions::new()
@@ -46,7 +45,7 @@ pub fn setup_logger() -> Result<(), fern::InitError> {
                     message
                 ))
             })
-            .level(log::LevelFilter::Info)
+            .level(level)
             .chain(fern::log_file(log_file_path)?)
             .apply()?;
         Ok(())
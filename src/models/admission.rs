@@ -0,0 +1,229 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::models::packet::Packet;
+use crate::models::rng::{stream_rng, RngStream};
+
+use chrono::{DateTime, Local, Duration};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+
+/// Behaviour of the pending queue when packets arrive faster than they are allocated
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum OverflowPolicy {
+    #[default]
+    TailDrop,                // Drop the arriving packet when the queue is full
+    HeadDrop,                // Drop the packet at the head of the queue to make room
+    DropOldestExpired,       // Drop the longest waiting packet past the time threshold, else the arriving one
+    Red(RedParams),          // Random early detection on the average queue length
+}
+
+/// Parameters of random early detection, thresholds are fractions of the queue capacity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedParams {
+    pub min_threshold: f64,      // Average length below which no packet is dropped
+    pub max_threshold: f64,      // Average length from which every arriving packet is dropped
+    pub max_probability: f64,    // Drop probability reached at the maximum threshold
+    pub weight: f64,             // Weight of the current length in the moving average
+}
+
+/// Names of all overflow policies
+pub const OVERFLOW_POLICY_NAMES: [&str; 4] = ["tail-drop", "head-drop", "drop-oldest-expired", "red"];
+
+impl Default for RedParams {
+    fn default() -> Self {
+        RedParams {
+            min_threshold: 0.25,
+            max_threshold: 0.75,
+            max_probability: 0.1,
+            weight: 0.002,
+        }
+    }
+}
+
+impl OverflowPolicy {
+    /// Build a policy from its name, with the default parameters
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tail-drop" => Some(OverflowPolicy::TailDrop),
+            "head-drop" => Some(OverflowPolicy::HeadDrop),
+            "drop-oldest-expired" => Some(OverflowPolicy::DropOldestExpired),
+            "red" => Some(OverflowPolicy::Red(RedParams::default())),
+            _ => None,
+        }
+    }
+}
+
+/// Decision on a packet arriving at the pending queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Admit,                           // Enqueue the packet
+    Evict(usize, &'static str),      // Drop the queued packet at the index for the reason, then enqueue
+    Reject(&'static str),            // Drop the arriving packet for the reason
+}
+
+/// Admission control of the pending queue
+#[derive(Debug)]
+pub struct AdmissionControl {
+    policy: OverflowPolicy,  // Behaviour on overflow
+    capacity: usize,         // Capacity of the pending queue
+    average: f64,            // Moving average of the queue length seen by arrivals
+    rng: ChaCha8Rng,         // Admission stream, draws early drops
+}
+
+impl AdmissionControl {
+    /// Admission control of a queue of the given capacity, random drops use the admission stream of `seed`
+    pub fn new(policy: OverflowPolicy, capacity: usize, seed: u64) -> Self {
+        AdmissionControl {
+            policy,
+            capacity,
+            average: 0.0,
+            rng: stream_rng(seed, RngStream::Admission),
+        }
+    }
+
+    /// Decide on a packet arriving at `now`, packets waiting longer than `time_threshold` are expired
+    pub fn decide(&mut self, pending_queue: &VecDeque<Packet>, now: DateTime<Local>, time_threshold: Duration) -> Admission {
+        let length = pending_queue.len();
+        let full = length >= self.capacity;
+
+        match &self.policy {
+            OverflowPolicy::TailDrop if full => Admission::Reject("tail-drop"),
+            OverflowPolicy::HeadDrop if full => Admission::Evict(0, "head-drop"),
+            OverflowPolicy::DropOldestExpired if full => {
+                let oldest = pending_queue
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| now.signed_duration_since(p.arrival_time) > time_threshold)
+                    .min_by_key(|(_, p)| p.arrival_time);
+                match oldest {
+                    Some((index, _)) => Admission::Evict(index, "expired"),
+                    None => Admission::Reject("tail-drop"),
+                }
+            }
+            OverflowPolicy::Red(params) => {
+                self.average += params.weight * (length as f64 - self.average);
+                let min = params.min_threshold * self.capacity as f64;
+                let max = params.max_threshold * self.capacity as f64;
+
+                let probability = if self.average < min {
+                    0.0
+                } else if self.average >= max {
+                    1.0
+                } else {
+                    params.max_probability * (self.average - min) / (max - min)
+                };
+
+                if full {
+                    Admission::Reject("tail-drop")
+                } else if probability > 0.0 && self.rng.gen::<f64>() < probability {
+                    Admission::Reject("red")
+                } else {
+                    Admission::Admit
+                }
+            }
+            _ => Admission::Admit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::phy::PhyParams;
+
+    /// Pending queue of packets that arrived the given milliseconds before `now`
+    fn queue(now: DateTime<Local>, waited_ms: &[i64]) -> VecDeque<Packet> {
+        waited_ms
+            .iter()
+            .enumerate()
+            .map(|(i, &waited)| Packet::from_phy(i as u32, PhyParams::default(), now - Duration::milliseconds(waited)))
+            .collect()
+    }
+
+    #[test]
+    fn tail_drop_rejects_the_arriving_packet_when_full() {
+        let now = Local::now();
+        let mut admission = AdmissionControl::new(OverflowPolicy::TailDrop, 3, 1);
+        assert_eq!(admission.decide(&queue(now, &[30, 20]), now, Duration::milliseconds(10)), Admission::Admit);
+        assert_eq!(admission.decide(&queue(now, &[30, 20, 10]), now, Duration::milliseconds(10)), Admission::Reject("tail-drop"));
+    }
+
+    #[test]
+    fn head_drop_evicts_the_front_packet_when_full() {
+        let now = Local::now();
+        let mut admission = AdmissionControl::new(OverflowPolicy::HeadDrop, 3, 1);
+        assert_eq!(admission.decide(&queue(now, &[30, 20]), now, Duration::milliseconds(10)), Admission::Admit);
+        assert_eq!(admission.decide(&queue(now, &[30, 20, 10]), now, Duration::milliseconds(10)), Admission::Evict(0, "head-drop"));
+    }
+
+    #[test]
+    fn drop_oldest_expired_evicts_the_longest_waiting_expired_packet() {
+        let now = Local::now();
+        let mut admission = AdmissionControl::new(OverflowPolicy::DropOldestExpired, 3, 1);
+        // Reordered queues, as shortest-job-first keeps them, still lose the packet that waited longest
+        let pending = queue(now, &[20, 50, 5]);
+        assert_eq!(admission.decide(&pending, now, Duration::milliseconds(10)), Admission::Evict(1, "expired"));
+    }
+
+    #[test]
+    fn drop_oldest_expired_rejects_the_arriving_packet_without_expired_ones() {
+        let now = Local::now();
+        let mut admission = AdmissionControl::new(OverflowPolicy::DropOldestExpired, 3, 1);
+        let pending = queue(now, &[20, 50, 5]);
+        assert_eq!(admission.decide(&pending, now, Duration::milliseconds(100)), Admission::Reject("tail-drop"));
+    }
+
+    #[test]
+    fn red_admits_below_the_minimum_threshold() {
+        let now = Local::now();
+        let params = RedParams { weight: 1.0, ..RedParams::default() };
+        let mut admission = AdmissionControl::new(OverflowPolicy::Red(params), 100, 1);
+        let pending = queue(now, &[0; 24]);
+        assert!((0..1000).all(|_| admission.decide(&pending, now, Duration::milliseconds(10)) == Admission::Admit));
+    }
+
+    #[test]
+    fn red_drops_every_arrival_from_the_maximum_threshold() {
+        let now = Local::now();
+        let params = RedParams { weight: 1.0, ..RedParams::default() };
+        let mut admission = AdmissionControl::new(OverflowPolicy::Red(params), 100, 1);
+        let pending = queue(now, &[0; 75]);
+        assert!((0..1000).all(|_| admission.decide(&pending, now, Duration::milliseconds(10)) == Admission::Reject("red")));
+    }
+
+    #[test]
+    fn red_drops_with_the_probability_between_the_thresholds() {
+        let now = Local::now();
+        let params = RedParams { weight: 1.0, max_probability: 0.2, ..RedParams::default() };
+        let mut admission = AdmissionControl::new(OverflowPolicy::Red(params), 100, 1);
+        // Halfway between the thresholds, half of the maximum probability
+        let pending = queue(now, &[0; 50]);
+        let dropped = (0..10_000).filter(|_| admission.decide(&pending, now, Duration::milliseconds(10)) == Admission::Reject("red")).count();
+        assert!((900..1100).contains(&dropped), "{} early drops", dropped);
+    }
+
+    #[test]
+    fn red_drops_the_arriving_packet_when_full() {
+        let now = Local::now();
+        let mut admission = AdmissionControl::new(OverflowPolicy::Red(RedParams::default()), 3, 1);
+        assert_eq!(admission.decide(&queue(now, &[30, 20, 10]), now, Duration::milliseconds(10)), Admission::Reject("tail-drop"));
+    }
+}
\ No newline at end of file
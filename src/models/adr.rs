@@ -0,0 +1,376 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::models::packet::{Packet, PacketStream};
+use crate::models::phy::transmit_energy_mj;
+use crate::models::region::Region;
+use crate::models::statistics::{AdrReport, SfInterval};
+
+use log::info;
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// SNR the demodulator needs at SF7 to SF12 in dB
+const REQUIRED_SNR_DB: [f64; 6] = [-7.5, -10.0, -12.5, -15.0, -17.5, -20.0];
+
+/// Power change of one ADR step in dB, one step of SNR margin is worth as much
+const STEP_DB: f64 = 3.0;
+
+/// Most intervals the run is split into to report the spreading factors over time
+const SF_INTERVALS: usize = 10;
+
+/// Initial width of these intervals in microseconds, doubled whenever the run outgrows them
+const SF_INTERVAL_US: i64 = 1000;
+
+/// Parameters of the adaptive data rate of the network server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdrConfig {
+    pub enabled: bool,              // Whether the network server adapts the data rate and power of the nodes
+    pub margin_db: f64,             // Installation margin kept above the SNR the spreading factor needs
+    pub history: usize,             // Uplinks the best SNR is taken over before deciding
+    pub min_tx_power_dbm: f64,      // Lowest power the nodes may be set to
+}
+
+impl Default for AdrConfig {
+    fn default() -> Self {
+        AdrConfig {
+            enabled: false,
+            margin_db: 10.0,
+            history: 20,
+            min_tx_power_dbm: 2.0,
+        }
+    }
+}
+
+impl AdrConfig {
+    /// Check the parameters, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.margin_db.is_finite() {
+            return Err(String::from("margin_db must be a finite number"));
+        }
+        if self.history == 0 {
+            return Err(String::from("history must be at least 1"));
+        }
+        if !self.min_tx_power_dbm.is_finite() {
+            return Err(String::from("min_tx_power_dbm must be a finite number"));
+        }
+        Ok(())
+    }
+}
+
+/// Settings the network server chose for a node and the SNRs they were chosen from
+#[derive(Debug, Default)]
+struct NodeAdr {
+    spreading_factor: Option<u8>,       // Spreading factor the node was set to, its own until the first command
+    tx_power_dbm: Option<f64>,          // Power the node was set to, its own until the first command
+    max_tx_power_dbm: Option<f64>,      // Power the node starts at, the ADR never goes above it
+    pending: Option<(u8, Option<f64>)>, // Settings decided but not yet received by the node in a downlink
+    snr: VecDeque<(u32, f64)>,          // Frame counter and best SNR of the last uplinks at the current settings
+}
+
+impl NodeAdr {
+    /// Switch to new settings, the SNRs at the previous ones no longer count
+    fn apply(&mut self, spreading_factor: u8, tx_power_dbm: Option<f64>) {
+        self.spreading_factor = Some(spreading_factor);
+        self.tx_power_dbm = tx_power_dbm;
+        self.snr.clear();
+    }
+}
+
+/// Adaptive data rate of the network server, shared with the nodes applying its commands
+#[derive(Debug, Clone, Default)]
+pub struct Adr {
+    inner: Arc<Mutex<AdrState>>,
+}
+
+#[derive(Debug, Default)]
+struct AdrState {
+    config: AdrConfig,                  // Margin, history length and power floor
+    region: Option<Region>,             // Region whose data rates bound the spreading factor
+    via_downlink: bool,                 // Whether the commands reach the nodes in downlinks, at once otherwise
+    nodes: HashMap<u32, NodeAdr>,       // Settings and SNR history of each node
+    commands: u64,                      // Changes of settings issued so far
+    first_uplink: Option<DateTime<Local>>, // Time of the first uplink, the intervals start from
+    interval_us: i64,                   // Width of the intervals
+    sf_intervals: Vec<[u64; 6]>,        // Uplinks sent at SF7 to SF12 in each interval
+    energy_mj: f64,                     // Energy of the uplinks at the settings they were sent with
+    fixed_energy_mj: f64,               // Energy of the uplinks at the settings of their nodes
+}
+
+impl Adr {
+    /// ADR with the given parameters, spreading factors bounded by the data rates of the region
+    ///
+    /// With `via_downlink` a node applies a command once it receives a downlink, otherwise as soon as it is issued
+    pub fn new(config: AdrConfig, region: Option<Region>, via_downlink: bool) -> Self {
+        let state = AdrState { config, region, via_downlink, interval_us: SF_INTERVAL_US, ..AdrState::default() };
+        Adr { inner: Arc::new(Mutex::new(state)) }
+    }
+
+    /// Whether the network server adapts the nodes
+    pub fn is_enabled(&self) -> bool {
+        self.inner.lock().unwrap().config.enabled
+    }
+
+    /// Record the SNR of a delivered copy of an uplink and change the settings of its node once enough are known
+    ///
+    /// Copies of the same uplink from several gateways count once, with the best SNR.
+    /// Uplinks sent before the last change are ignored.
+    pub fn observe(&self, packet: &Packet) {
+        let mut state = self.inner.lock().unwrap();
+        if !state.config.enabled {
+            return;
+        }
+
+        let history = state.config.history;
+        let node = state.nodes.entry(packet.node_id).or_default();
+        if node.spreading_factor.is_some_and(|sf| sf != packet.phy.spreading_factor)
+            || node.tx_power_dbm.is_some() && node.tx_power_dbm != packet.tx_power_dbm {
+            return;
+        }
+        match node.snr.back_mut() {
+            Some((frame_counter, snr)) if *frame_counter == packet.frame_counter => *snr = snr.max(packet.snr),
+            _ => {
+                node.snr.push_back((packet.frame_counter, packet.snr));
+                if node.snr.len() > history {
+                    node.snr.pop_front();
+                }
+            }
+        }
+        if node.snr.len() < history || node.pending.is_some() {
+            return;
+        }
+
+        let (spreading_factor, tx_power_dbm) = state.decide(packet);
+        let via_downlink = state.via_downlink;
+        let node = state.nodes.get_mut(&packet.node_id).unwrap();
+        if spreading_factor != packet.phy.spreading_factor || tx_power_dbm != packet.tx_power_dbm {
+            info!("\u{1F4F6}: ADR sets node {} from SF{} to SF{}, power {} dBm", packet.node_id,
+                packet.phy.spreading_factor, spreading_factor, tx_power_dbm.map_or(String::from("unchanged"), |p| p.to_string()));
+            if via_downlink {
+                node.pending = Some((spreading_factor, tx_power_dbm));
+            } else {
+                node.apply(spreading_factor, tx_power_dbm);
+            }
+            state.commands += 1;
+        }
+    }
+
+    /// Whether a command for the node waits for a downlink
+    pub fn has_command(&self, node_id: u32) -> bool {
+        self.inner.lock().unwrap().nodes.get(&node_id).is_some_and(|node| node.pending.is_some())
+    }
+
+    /// The node receives a downlink, carrying the command waiting for it if any
+    pub fn downlink_received(&self, node_id: u32) {
+        let mut state = self.inner.lock().unwrap();
+        if let Some(node) = state.nodes.get_mut(&node_id) {
+            if let Some((spreading_factor, tx_power_dbm)) = node.pending.take() {
+                node.apply(spreading_factor, tx_power_dbm);
+            }
+        }
+    }
+
+    /// Apply the settings of its node to an uplink and account the energy it takes
+    pub fn apply(&self, packet: &mut Packet) {
+        let mut state = self.inner.lock().unwrap();
+        let fixed_energy = transmit_energy_mj(packet.tx_power_dbm.unwrap_or_default(), packet.phy.time_on_air());
+
+        let node = state.nodes.entry(packet.node_id).or_default();
+        node.max_tx_power_dbm = node.max_tx_power_dbm.or(packet.tx_power_dbm);
+        if let Some(spreading_factor) = node.spreading_factor {
+            packet.phy.spreading_factor = spreading_factor;
+            packet.processing_time = packet.phy.time_on_air();
+        }
+        if node.tx_power_dbm.is_some() {
+            packet.tx_power_dbm = node.tx_power_dbm;
+        }
+
+        state.fixed_energy_mj += fixed_energy;
+        state.energy_mj += transmit_energy_mj(packet.tx_power_dbm.unwrap_or_default(), packet.phy.time_on_air());
+        state.count_uplink(packet.arrival_time, packet.phy.spreading_factor);
+    }
+
+    /// Changes issued, energy saved against fixed settings and spreading factors over time
+    pub fn report(&self) -> AdrReport {
+        let state = self.inner.lock().unwrap();
+        if state.first_uplink.is_none() {
+            return AdrReport::default();
+        }
+
+        let sf_distribution = state.sf_intervals
+            .iter()
+            .enumerate()
+            .map(|(i, &uplinks)| SfInterval { start_ms: (i as i64 * state.interval_us) as f64 / 1000.0, uplinks })
+            .collect();
+
+        AdrReport {
+            commands: state.commands,
+            energy_mj: state.energy_mj,
+            fixed_energy_mj: state.fixed_energy_mj,
+            energy_savings: if state.fixed_energy_mj > 0.0 { 1.0 - state.energy_mj / state.fixed_energy_mj } else { 0.0 },
+            sf_distribution,
+        }
+    }
+}
+
+impl AdrState {
+    /// Count an uplink sent at `time` in its interval, merging the intervals pairwise until the run fits in `SF_INTERVALS`
+    fn count_uplink(&mut self, time: DateTime<Local>, spreading_factor: u8) {
+        let first = *self.first_uplink.get_or_insert(time);
+        let offset_us = (time - first).num_microseconds().unwrap_or(i64::MAX).max(0);
+        while offset_us / self.interval_us >= SF_INTERVALS as i64 {
+            self.sf_intervals = self.sf_intervals
+                .chunks(2)
+                .map(|pair| std::array::from_fn(|sf| pair.iter().map(|uplinks| uplinks[sf]).sum()))
+                .collect();
+            self.interval_us *= 2;
+        }
+
+        let interval = (offset_us / self.interval_us) as usize;
+        if self.sf_intervals.len() <= interval {
+            self.sf_intervals.resize(interval + 1, [0; 6]);
+        }
+        self.sf_intervals[interval][usize::from(spreading_factor.clamp(7, 12) - 7)] += 1;
+    }
+
+    /// Spreading factor and power of a node from the best SNR of its history, as the LoRaWAN reference algorithm
+    ///
+    /// Every 3 dB of margin lowers the spreading factor down to the fastest data rate, then the power down to its floor;
+    /// a missing margin raises the power back up to that of the node
+    fn decide(&self, packet: &Packet) -> (u8, Option<f64>) {
+        let node = &self.nodes[&packet.node_id];
+        let best_snr = node.snr.iter().map(|(_, snr)| *snr).fold(f64::NEG_INFINITY, f64::max);
+        let mut spreading_factor = packet.phy.spreading_factor;
+        let mut tx_power_dbm = packet.tx_power_dbm;
+
+        let margin = best_snr - REQUIRED_SNR_DB[usize::from(spreading_factor - 7)] - self.config.margin_db;
+        let mut steps = (margin / STEP_DB).trunc() as i32;
+        let fastest = (7..spreading_factor)
+            .find(|&sf| self.region.is_none_or(|region| region.allows(sf, packet.phy.bandwidth)))
+            .unwrap_or(spreading_factor);
+        while steps > 0 && spreading_factor > fastest {
+            spreading_factor -= 1;
+            steps -= 1;
+        }
+        if let (Some(power), Some(max)) = (tx_power_dbm.as_mut(), node.max_tx_power_dbm) {
+            let min = self.config.min_tx_power_dbm.min(max);
+            while steps > 0 && *power > min {
+                *power = (*power - STEP_DB).max(min);
+                steps -= 1;
+            }
+            while steps < 0 && *power < max {
+                *power = (*power + STEP_DB).min(max);
+                steps += 1;
+            }
+        }
+        (spreading_factor, tx_power_dbm)
+    }
+}
+
+/// Uplinks of the nodes sent with the settings the ADR of the network server chose for them
+pub struct AdaptedUplinks {
+    packets: PacketStream,              // Uplinks at the settings of their nodes, in arrival order
+    adr: Adr,                           // Settings chosen for each node
+}
+
+impl AdaptedUplinks {
+    /// Apply the commands of `adr` to `packets`
+    pub fn new(packets: PacketStream, adr: Adr) -> Self {
+        AdaptedUplinks { packets, adr }
+    }
+}
+
+impl Iterator for AdaptedUplinks {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        let mut packet = self.packets.next()?;
+        self.adr.apply(&mut packet);
+        Some(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::phy::PhyParams;
+    use chrono::Duration;
+
+    fn uplink(spreading_factor: u8, tx_power_dbm: f64) -> Packet {
+        let phy = PhyParams { spreading_factor, ..PhyParams::default() };
+        Packet { node_id: 1, tx_power_dbm: Some(tx_power_dbm), ..Packet::from_phy(0, phy, Local::now()) }
+    }
+
+    /// Settings decided for an uplink when the best SNR of the node is `snr`
+    fn decide(packet: &Packet, snr: f64, max_tx_power_dbm: f64) -> (u8, Option<f64>) {
+        let node = NodeAdr { max_tx_power_dbm: Some(max_tx_power_dbm), snr: VecDeque::from([(0, snr)]), ..NodeAdr::default() };
+        let state = AdrState { config: AdrConfig::default(), nodes: HashMap::from([(1, node)]), ..AdrState::default() };
+        state.decide(packet)
+    }
+
+    #[test]
+    fn each_3_db_of_margin_lowers_the_spreading_factor() {
+        // SF12 needs -20 dB, with the 10 dB installation margin 9.5 dB more are three steps
+        assert_eq!(decide(&uplink(12, 14.0), -0.5, 14.0), (9, Some(14.0)));
+        assert_eq!(decide(&uplink(12, 14.0), -8.5, 14.0), (12, Some(14.0)));
+    }
+
+    #[test]
+    fn remaining_steps_lower_the_power_to_its_floor() {
+        // 30 dB are ten steps, five down to SF7 then four down to the 2 dBm floor
+        assert_eq!(decide(&uplink(12, 14.0), 20.0, 14.0), (7, Some(2.0)));
+    }
+
+    #[test]
+    fn missing_margin_raises_the_power_up_to_that_of_the_node() {
+        // SF9 needs -12.5 dB, 4 dB below the margin is one step up
+        assert_eq!(decide(&uplink(9, 8.0), -6.5, 14.0), (9, Some(11.0)));
+        assert_eq!(decide(&uplink(9, 8.0), -30.0, 14.0), (9, Some(14.0)));
+    }
+
+    #[test]
+    fn command_is_issued_once_the_history_is_full() {
+        let config = AdrConfig { enabled: true, history: 3, ..AdrConfig::default() };
+        let adr = Adr::new(config, None, false);
+        for frame_counter in 0..3 {
+            let mut packet = Packet { frame_counter, snr: 10.0, ..uplink(12, 14.0) };
+            adr.apply(&mut packet);
+            adr.observe(&packet);
+        }
+        assert_eq!(adr.report().commands, 1);
+
+        let mut packet = uplink(12, 14.0);
+        adr.apply(&mut packet);
+        assert_eq!(packet.phy.spreading_factor, 7);
+    }
+
+    #[test]
+    fn sf_intervals_double_until_the_run_fits() {
+        let mut state = AdrState { interval_us: SF_INTERVAL_US, ..AdrState::default() };
+        let start = Local::now();
+        state.count_uplink(start, 12);
+        state.count_uplink(start + Duration::microseconds(2_500), 9);
+        state.count_uplink(start + Duration::microseconds(25_000), 7);
+
+        assert_eq!(state.interval_us, 4_000);
+        assert_eq!(state.sf_intervals.len(), 7);
+        assert_eq!(state.sf_intervals[0], [0, 0, 1, 0, 0, 1]);
+        assert_eq!(state.sf_intervals[6], [1, 0, 0, 0, 0, 0]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,218 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use chrono::Duration;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+/// Process generating the arrival times of the packets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "process", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum ArrivalProcess {
+    /// Exponential inter-arrival times
+    Poisson {
+        rate: f64,                  // Mean packets per second
+    },
+    /// One packet per period from a random phase within the first period, each delayed by a uniform jitter
+    Periodic {
+        period_ms: f64,             // Time between two nominal arrivals
+        jitter_ms: f64,             // Largest delay of an arrival after its nominal time, at most the period
+    },
+    /// Arrivals spread uniformly over a window
+    Uniform {
+        duration_ms: f64,           // Length of the window starting at the beginning of the run
+    },
+    /// Markov-modulated Poisson process, cycling through states of different rates
+    Mmpp {
+        rates: Vec<f64>,            // Mean packets per second in each state
+        mean_sojourn_ms: Vec<f64>,  // Mean time spent in each state
+    },
+}
+
+impl Default for ArrivalProcess {
+    fn default() -> Self {
+        ArrivalProcess::Poisson { rate: 10.0 }
+    }
+}
+
+impl ArrivalProcess {
+    /// Check the parameters, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |value: f64| value.is_finite() && value > 0.0;
+        match self {
+            ArrivalProcess::Poisson { rate } => {
+                if !positive(*rate) {
+                    return Err(format!("rate must be positive, got {}", rate));
+                }
+            }
+            ArrivalProcess::Periodic { period_ms, jitter_ms } => {
+                if !positive(*period_ms) {
+                    return Err(format!("period_ms must be positive, got {}", period_ms));
+                }
+                if !(0.0..=*period_ms).contains(jitter_ms) {
+                    return Err(format!("jitter_ms must be within 0 to period_ms, got {}", jitter_ms));
+                }
+            }
+            ArrivalProcess::Uniform { duration_ms } => {
+                if !duration_ms.is_finite() || *duration_ms < 0.0 {
+                    return Err(format!("duration_ms must not be negative, got {}", duration_ms));
+                }
+            }
+            ArrivalProcess::Mmpp { rates, mean_sojourn_ms } => {
+                if rates.is_empty() || rates.len() != mean_sojourn_ms.len() {
+                    return Err(String::from("rates and mean_sojourn_ms must have the same, non-zero length"));
+                }
+                if rates.iter().any(|r| !r.is_finite() || *r < 0.0) || !rates.iter().any(|r| *r > 0.0) {
+                    return Err(format!("rates must not be negative and at least one must be positive, got {:?}", rates));
+                }
+                if !mean_sojourn_ms.iter().all(|t| positive(*t)) {
+                    return Err(format!("mean_sojourn_ms must be positive, got {:?}", mean_sojourn_ms));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Exponentially distributed sample of the given mean
+fn exponential<R: Rng>(rng: &mut R, mean: f64) -> f64 {
+    -mean * (1.0 - rng.gen::<f64>()).ln()
+}
+
+/// Draws the arrival times of a process one after another, in ascending order
+#[derive(Debug)]
+pub struct ArrivalSampler {
+    process: ArrivalProcess,        // Process the arrivals follow
+    remaining: u64,                 // Arrivals still to draw
+    index: u64,                     // Number of arrivals drawn
+    time_ms: f64,                   // Offset of the last arrival from the start of the run
+    state: usize,                   // Current state of a Markov-modulated process
+    state_end_ms: f64,              // Offset at which the current state is left
+    phase_ms: f64,                  // Nominal offset of the first arrival of a periodic process
+}
+
+impl ArrivalSampler {
+    /// Sampler of `count` arrivals of the process
+    pub fn new(process: ArrivalProcess, count: u64) -> Self {
+        ArrivalSampler {
+            process,
+            remaining: count,
+            index: 0,
+            time_ms: 0.0,
+            state: 0,
+            state_end_ms: f64::NAN,
+            phase_ms: 0.0,
+        }
+    }
+
+    /// Offset of the next arrival from the start of the run
+    pub fn next_offset<R: Rng>(&mut self, rng: &mut R) -> Duration {
+        self.time_ms = match &self.process {
+            ArrivalProcess::Poisson { rate } => self.time_ms + exponential(rng, 1000.0 / rate),
+            ArrivalProcess::Periodic { period_ms, jitter_ms } => {
+                // Each sampler starts at its own phase, so periodic nodes do not all transmit at once
+                if self.index == 0 {
+                    self.phase_ms = rng.gen::<f64>() * period_ms;
+                }
+                self.phase_ms + self.index as f64 * period_ms + rng.gen::<f64>() * jitter_ms
+            }
+            ArrivalProcess::Uniform { duration_ms } => {
+                // Smallest of the remaining uniform arrivals, so they come out sorted
+                let fraction = 1.0 - rng.gen::<f64>().powf(1.0 / self.remaining.max(1) as f64);
+                self.time_ms + (duration_ms - self.time_ms) * fraction
+            }
+            ArrivalProcess::Mmpp { rates, mean_sojourn_ms } => {
+                if self.state_end_ms.is_nan() {
+                    self.state_end_ms = exponential(rng, mean_sojourn_ms[0]);
+                }
+                let mut time = self.time_ms;
+                loop {
+                    // Exponential gaps are memoryless, so the gap is redrawn after a state change
+                    let rate = rates[self.state];
+                    if rate > 0.0 {
+                        let arrival = time + exponential(rng, 1000.0 / rate);
+                        if arrival < self.state_end_ms {
+                            break arrival;
+                        }
+                    }
+                    time = self.state_end_ms;
+                    self.state = (self.state + 1) % rates.len();
+                    self.state_end_ms = time + exponential(rng, mean_sojourn_ms[self.state]);
+                }
+            }
+        };
+        self.index += 1;
+        self.remaining = self.remaining.saturating_sub(1);
+        Duration::microseconds((self.time_ms * 1000.0).round() as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::rng::{stream_rng, RngStream};
+
+    /// Offsets in ms of `count` arrivals of the process
+    fn offsets_ms(process: ArrivalProcess, count: u64) -> Vec<f64> {
+        let mut rng = stream_rng(1, RngStream::Arrival);
+        let mut sampler = ArrivalSampler::new(process, count);
+        (0..count).map(|_| sampler.next_offset(&mut rng).num_microseconds().unwrap() as f64 / 1000.0).collect()
+    }
+
+    /// Mean arrivals per second over the span of the offsets
+    fn mean_rate(offsets: &[f64]) -> f64 {
+        offsets.len() as f64 / (offsets[offsets.len() - 1] / 1000.0)
+    }
+
+    #[test]
+    fn poisson_mean_rate() {
+        let rate = mean_rate(&offsets_ms(ArrivalProcess::Poisson { rate: 10.0 }, 100_000));
+        assert!((rate - 10.0).abs() < 0.2, "mean rate {}", rate);
+    }
+
+    #[test]
+    fn mmpp_mean_rate_is_weighted_by_sojourn() {
+        let process = ArrivalProcess::Mmpp { rates: vec![20.0, 0.0], mean_sojourn_ms: vec![1000.0, 3000.0] };
+        let rate = mean_rate(&offsets_ms(process, 100_000));
+        assert!((rate - 5.0).abs() < 0.25, "mean rate {}", rate);
+    }
+
+    #[test]
+    fn periodic_arrivals_keep_their_phase() {
+        let offsets = offsets_ms(ArrivalProcess::Periodic { period_ms: 1000.0, jitter_ms: 0.0 }, 5);
+        assert!((0.0..1000.0).contains(&offsets[0]));
+        for pair in offsets.windows(2) {
+            assert!((pair[1] - pair[0] - 1000.0).abs() < 0.002);
+        }
+    }
+
+    #[test]
+    fn periodic_samplers_draw_their_own_phase() {
+        let mut rng = stream_rng(1, RngStream::Arrival);
+        let process = ArrivalProcess::Periodic { period_ms: 1000.0, jitter_ms: 0.0 };
+        let first: Vec<Duration> = (0..2).map(|_| ArrivalSampler::new(process.clone(), 1).next_offset(&mut rng)).collect();
+        assert_ne!(first[0], first[1]);
+    }
+
+    #[test]
+    fn uniform_arrivals_are_sorted_within_the_window() {
+        let offsets = offsets_ms(ArrivalProcess::Uniform { duration_ms: 1000.0 }, 1000);
+        assert!(offsets.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(offsets[offsets.len() - 1] <= 1000.0);
+        let mean = offsets.iter().sum::<f64>() / offsets.len() as f64;
+        assert!((mean - 500.0).abs() < 30.0, "mean offset {}", mean);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,120 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::packet::Packet;
+
+use chrono::Duration;
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+
+/// Frequency, bandwidth and capacity of a gateway channel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelConfig {
+    pub frequency: u32,             // Frequency the channel is tuned to in Hz
+    #[serde(default = "default_bandwidth")]
+    pub bandwidth: u32,             // Bandwidth in Hz
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,            // Capacity of the channel queue
+}
+
+fn default_bandwidth() -> u32 {
+    125_000
+}
+
+fn default_capacity() -> usize {
+    10
+}
+
+impl ChannelConfig {
+    /// Channel with the default bandwidth and capacity
+    pub fn new(frequency: u32) -> Self {
+        ChannelConfig {
+            frequency,
+            bandwidth: default_bandwidth(),
+            capacity: default_capacity(),
+        }
+    }
+}
+
+/// Channel of a gateway with its queue of packets waiting to be processed
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub frequency: u32,             // Frequency the channel is tuned to in Hz
+    pub bandwidth: u32,             // Bandwidth in Hz
+    pub capacity: usize,            // Capacity of the queue
+    pub queue: VecDeque<Packet>,    // Packets waiting to be processed
+}
+
+impl Channel {
+    /// Create an empty channel
+    pub fn new(config: &ChannelConfig) -> Self {
+        Channel {
+            frequency: config.frequency,
+            bandwidth: config.bandwidth,
+            capacity: config.capacity,
+            queue: VecDeque::with_capacity(config.capacity),
+        }
+    }
+
+    /// Check if the queue is full
+    pub fn is_full(&self) -> bool {
+        self.queue.len() >= self.capacity
+    }
+
+    /// Check if the channel is tuned to the frequency and bandwidth the packet was received on
+    pub fn tuned_to(&self, packet: &Packet) -> bool {
+        self.frequency == packet.phy.frequency && self.bandwidth == packet.phy.bandwidth
+    }
+
+    /// Check if the channel can take the packet: not full and tuned to it
+    pub fn accepts(&self, packet: &Packet) -> bool {
+        !self.is_full() && self.tuned_to(packet)
+    }
+
+    /// Total processing time of the packets waiting in the queue
+    pub fn time_load(&self) -> Duration {
+        self.queue.iter().map(|p| p.processing_time).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::phy::PhyParams;
+    use chrono::Local;
+
+    fn packet(frequency: u32, bandwidth: u32) -> Packet {
+        Packet::from_phy(1, PhyParams { frequency, bandwidth, ..PhyParams::default() }, Local::now())
+    }
+
+    #[test]
+    fn channel_accepts_only_its_frequency_and_bandwidth() {
+        let channel = Channel::new(&ChannelConfig::new(868_100_000));
+        assert!(channel.accepts(&packet(868_100_000, 125_000)));
+        assert!(!channel.accepts(&packet(868_300_000, 125_000)));
+        assert!(!channel.accepts(&packet(868_100_000, 250_000)));
+    }
+
+    #[test]
+    fn full_channel_accepts_nothing() {
+        let mut channel = Channel::new(&ChannelConfig { capacity: 1, ..ChannelConfig::new(868_100_000) });
+        channel.queue.push_back(packet(868_100_000, 125_000));
+        assert!(channel.tuned_to(&packet(868_100_000, 125_000)));
+        assert!(!channel.accepts(&packet(868_100_000, 125_000)));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,297 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::downlink::{Downlink, DownlinkConfig, ReceiveWindow};
+use crate::models::packet::Packet;
+use crate::models::phy::PhyParams;
+use crate::models::region::Region;
+use crate::models::rng::{stream_rng, RngStream};
+use crate::models::statistics::{ratio, ClassBReport, Samples};
+
+use chrono::{DateTime, Local, Duration};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Interval between two beacons in ms, beacons start at the multiples of it since the start of the run
+pub const BEACON_PERIOD_MS: i64 = 128_000;
+
+/// Time reserved for the beacon at the start of each beacon period in ms, no ping slot starts in it
+const BEACON_RESERVED_MS: i64 = 2_120;
+
+/// Length of a ping slot in ms
+const SLOT_MS: i64 = 30;
+
+/// Ping slots in a beacon period, after the reserved time
+const SLOTS: u64 = 4096;
+
+/// Beacon periods a downlink may wait for a free ping slot before it is given up
+const SEARCH_PERIODS: u64 = 4;
+
+/// Parameters of the Class B nodes, which open ping slots the network server sends downlinks in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClassBConfig {
+    pub enabled: bool,              // Whether the gateways beacon and the nodes open ping slots
+    pub periodicity: u8,            // Ping slots every 2^periodicity seconds, 0 to 7
+    pub downlink_interval_s: f64,   // Mean time between the downlinks the application sends each node
+}
+
+impl Default for ClassBConfig {
+    fn default() -> Self {
+        ClassBConfig {
+            enabled: false,
+            periodicity: 4,
+            downlink_interval_s: 3600.0,
+        }
+    }
+}
+
+impl ClassBConfig {
+    /// Check the parameters, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        if self.periodicity > 7 {
+            return Err(format!("periodicity must be within 0 to 7, got {}", self.periodicity));
+        }
+        if !self.downlink_interval_s.is_finite() || self.downlink_interval_s <= 0.0 {
+            return Err(format!("downlink_interval_s must be positive, got {}", self.downlink_interval_s));
+        }
+        Ok(())
+    }
+}
+
+/// Index of the beacon period containing `time`, periods are counted from `epoch`
+pub fn beacon_period(epoch: DateTime<Local>, time: DateTime<Local>) -> u64 {
+    (time - epoch).num_milliseconds().div_euclid(BEACON_PERIOD_MS).max(0) as u64
+}
+
+/// Start of the beacon of a beacon period counted from `epoch`
+pub fn beacon_start(epoch: DateTime<Local>, period: u64) -> DateTime<Local> {
+    epoch + Duration::milliseconds(period as i64 * BEACON_PERIOD_MS)
+}
+
+/// Offset of the first ping slot of a node in a beacon period, below `ping_period`
+///
+/// A hash of the beacon period and the node stands in for the AES of the specification,
+/// so that the slots of a node move from one period to the next and differ between nodes
+fn ping_offset(period: u64, node_id: u32, ping_period: u64) -> u64 {
+    let mut z = period.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ u64::from(node_id);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (z ^ (z >> 31)) % ping_period
+}
+
+/// Class B beacons and ping-slot downlinks, shared by the network server and the engine
+#[derive(Debug, Clone)]
+pub struct ClassB {
+    inner: Arc<Mutex<ClassBState>>,
+    config: ClassBConfig,               // Periodicity of the ping slots and downlink traffic
+    region: Region,                     // Region giving the beacon and ping-slot channels
+    downlink: DownlinkConfig,           // Payload and power of the downlinks
+}
+
+#[derive(Debug)]
+struct ClassBState {
+    routes: HashMap<u32, (usize, Packet)>, // Gateway index and first delivered copy of the last uplink of each node
+    requested: u64,                     // Downlinks the application asked for
+    sent: u64,                          // Downlinks scheduled in a ping slot
+    no_slot: u64,                       // Downlinks no free ping slot was found for
+    received: u64,                      // Downlinks heard by their node
+    latencies_ms: Samples,              // From the request to the end of each downlink sent
+    beacons: u64,                       // Beacons sent, counted once per gateway
+    beacon_airtime_ms: f64,             // Time on air of these beacons
+    rng: ChaCha8Rng,                    // Class B stream, draws the times of the downlinks
+}
+
+impl ClassB {
+    /// Class B of a region, downlinks with the payload and power of `downlink`, its random stream derives from `seed`
+    pub fn new(config: ClassBConfig, region: Region, downlink: DownlinkConfig, seed: u64) -> Self {
+        let state = ClassBState {
+            routes: HashMap::new(),
+            requested: 0,
+            sent: 0,
+            no_slot: 0,
+            received: 0,
+            latencies_ms: Samples::default(),
+            beacons: 0,
+            beacon_airtime_ms: 0.0,
+            rng: stream_rng(seed, RngStream::ClassB),
+        };
+        ClassB { inner: Arc::new(Mutex::new(state)), config, region, downlink }
+    }
+
+    /// Whether the gateways beacon and the nodes open ping slots
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Physical layer parameters of the beacon of a beacon period
+    pub fn beacon(&self, period: u64) -> PhyParams {
+        let (frequency, data_rate, payload_length) = self.region.beacon(period);
+        PhyParams {
+            frequency,
+            spreading_factor: data_rate.spreading_factor,
+            bandwidth: data_rate.bandwidth,
+            payload_length,
+            preamble_length: 10,
+            explicit_header: false,
+            crc: false,
+            ..PhyParams::default()
+        }
+    }
+
+    /// A gateway beaconed for `time_on_air`
+    pub fn beacon_sent(&self, time_on_air: Duration) {
+        let mut state = self.inner.lock().unwrap();
+        state.beacons += 1;
+        state.beacon_airtime_ms += time_on_air.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0;
+    }
+
+    /// The gateway at `gateway` delivered the first copy of an uplink, returns whether its node was unknown so far
+    ///
+    /// The node switches to Class B with this uplink, the application starts sending it downlinks
+    pub fn route(&self, gateway: usize, packet: &Packet) -> bool {
+        self.inner.lock().unwrap().routes.insert(packet.node_id, (gateway, packet.clone())).is_none()
+    }
+
+    /// Instant of the next downlink the application sends a node, after one at `now`
+    pub fn next_request(&self, now: DateTime<Local>) -> DateTime<Local> {
+        let u: f64 = self.inner.lock().unwrap().rng.gen();
+        let interval_s = -self.config.downlink_interval_s * (1.0 - u).ln();
+        now + Duration::microseconds((interval_s * 1e6).round() as i64)
+    }
+
+    /// The application asks for a downlink to a node a gateway has delivered an uplink from,
+    /// returns the gateway to send it through and the last uplink of the node
+    pub fn request(&self, node_id: u32) -> (usize, Packet) {
+        let mut state = self.inner.lock().unwrap();
+        state.requested += 1;
+        state.routes.get(&node_id).cloned().expect("Class B downlinks go to nodes with a route")
+    }
+
+    /// Downlinks to the node of `uplink` in its ping slots from `from` on, in time order, over the search horizon,
+    /// the beacon periods are counted from `epoch`
+    pub fn ping_slots(&self, uplink: &Packet, epoch: DateTime<Local>, from: DateTime<Local>) -> Vec<Downlink> {
+        let pings = 1u64 << (7 - self.config.periodicity);
+        let ping_period = SLOTS / pings;
+        let first = beacon_period(epoch, from);
+
+        (first..first + SEARCH_PERIODS)
+            .flat_map(|period| {
+                let offset = ping_offset(period, uplink.node_id, ping_period);
+                (0..pings).map(move |n| (period, offset + n * ping_period))
+            })
+            .map(|(period, slot)| {
+                let start = beacon_start(epoch, period) + Duration::milliseconds(BEACON_RESERVED_MS + slot as i64 * SLOT_MS);
+                let (frequency, data_rate) = self.region.ping_slot(period, uplink.node_id);
+                Downlink::to(uplink, ReceiveWindow::PingSlot, frequency, data_rate, start, &self.downlink)
+            })
+            .filter(|downlink| downlink.start >= from)
+            .collect()
+    }
+
+    /// A downlink asked for at `requested_at` was scheduled in a ping slot
+    pub fn scheduled(&self, downlink: &Downlink, requested_at: DateTime<Local>) {
+        let mut state = self.inner.lock().unwrap();
+        state.sent += 1;
+        state.latencies_ms.push((downlink.end() - requested_at).num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0);
+    }
+
+    /// No free ping slot was found for a downlink
+    pub fn unscheduled(&self) {
+        self.inner.lock().unwrap().no_slot += 1;
+    }
+
+    /// A node heard a downlink in one of its ping slots
+    pub fn received(&self) {
+        self.inner.lock().unwrap().received += 1;
+    }
+
+    /// Class B statistics, the beacons against the `downlink_airtime_ms` of every other downlink
+    pub fn report(&self, downlink_airtime_ms: f64) -> ClassBReport {
+        let state = self.inner.lock().unwrap();
+        let airtime_ms = state.beacon_airtime_ms + downlink_airtime_ms;
+        ClassBReport {
+            requested: state.requested,
+            sent: state.sent,
+            no_slot: state.no_slot,
+            received: state.received,
+            delivery_ratio: ratio(state.received, state.requested),
+            latency_ms: state.latencies_ms.summary(),
+            beacons: state.beacons,
+            beacon_airtime_ms: state.beacon_airtime_ms,
+            beacon_overhead: state.beacon_airtime_ms / (state.beacons.max(1) * BEACON_PERIOD_MS as u64) as f64,
+            beacon_share: if airtime_ms > 0.0 { state.beacon_airtime_ms / airtime_ms } else { 0.0 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::downlink::DownlinkScheduler;
+
+    fn class_b() -> ClassB {
+        ClassB::new(ClassBConfig { enabled: true, ..ClassBConfig::default() }, Region::Eu868, DownlinkConfig::default(), 1)
+    }
+
+    fn uplink() -> Packet {
+        Packet { node_id: 3, ..Packet::from_phy(1, PhyParams::default(), Local::now()) }
+    }
+
+    #[test]
+    fn beacon_periods_count_from_the_epoch() {
+        let epoch = Local::now();
+        assert_eq!(beacon_period(epoch, epoch), 0);
+        assert_eq!(beacon_period(epoch, epoch + Duration::milliseconds(BEACON_PERIOD_MS - 1)), 0);
+        assert_eq!(beacon_period(epoch, epoch + Duration::milliseconds(BEACON_PERIOD_MS)), 1);
+        assert_eq!(beacon_start(epoch, 2), epoch + Duration::milliseconds(2 * BEACON_PERIOD_MS));
+    }
+
+    #[test]
+    fn ping_slots_do_not_depend_on_the_wall_clock() {
+        let offsets = |epoch: DateTime<Local>| -> Vec<Duration> {
+            class_b().ping_slots(&uplink(), epoch, epoch).iter().map(|downlink| downlink.start - epoch).collect()
+        };
+        let epoch = Local::now();
+        assert_eq!(offsets(epoch), offsets(epoch + Duration::milliseconds(12_345)));
+    }
+
+    #[test]
+    fn ping_slots_follow_the_periodicity_after_the_beacon() {
+        let epoch = Local::now();
+        let slots = class_b().ping_slots(&uplink(), epoch, epoch);
+        // 2^4 s periodicity, eight ping slots in each beacon period searched
+        assert_eq!(slots.len(), 8 * SEARCH_PERIODS as usize);
+        for downlink in &slots {
+            let period = beacon_period(epoch, downlink.start);
+            assert!(downlink.start >= beacon_start(epoch, period) + Duration::milliseconds(BEACON_RESERVED_MS));
+        }
+        assert!(slots.windows(2).all(|pair| pair[0].start < pair[1].start));
+    }
+
+    #[test]
+    fn gateway_is_busy_while_it_beacons() {
+        let epoch = Local::now();
+        let scheduler = DownlinkScheduler::with_beacons(epoch, class_b().beacon(0).time_on_air());
+        let next_beacon = beacon_start(epoch, 1);
+        assert!(scheduler.transmitting(next_beacon - Duration::milliseconds(10), next_beacon + Duration::milliseconds(10)));
+        assert!(!scheduler.transmitting(epoch + Duration::seconds(1), epoch + Duration::seconds(2)));
+    }
+}
\ No newline at end of file
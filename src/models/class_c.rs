@@ -0,0 +1,342 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::downlink::{Downlink, DownlinkConfig, ReceiveWindow};
+use crate::models::packet::Packet;
+use crate::models::region::{DataRate, Region};
+use crate::models::rng::{stream_rng, RngStream};
+use crate::models::statistics::{ratio, ClassCReport, DeviceFragments, Samples, Summary};
+
+use chrono::{DateTime, Local, Duration};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+/// Parameters of the Class C nodes, which listen on the RX2 channel whenever they do not transmit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClassCConfig {
+    pub enabled: bool,              // Whether the nodes listen continuously
+    pub downlink_interval_s: f64,   // Mean time between the unicast downlinks the application sends each node
+    pub multicast: MulticastConfig, // Session sending fragments to every node at once
+}
+
+impl Default for ClassCConfig {
+    fn default() -> Self {
+        ClassCConfig {
+            enabled: false,
+            downlink_interval_s: 3600.0,
+            multicast: MulticastConfig::default(),
+        }
+    }
+}
+
+/// Multicast session sending a file in fragments to the group of the Class C nodes, as FUOTA does
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MulticastConfig {
+    pub fragments: u32,                 // Fragments of the file, no session when 0
+    pub payload_length: u8,             // PHY payload of a fragment in bytes
+    pub spreading_factor: Option<u8>,   // Spreading factor of the fragments, that of RX2 when absent
+    pub start_s: f64,                   // Start of the session from the start of the run
+    pub interval_ms: f64,               // From the actual send of a fragment to the next one
+}
+
+impl Default for MulticastConfig {
+    fn default() -> Self {
+        MulticastConfig {
+            fragments: 0,
+            payload_length: 60,
+            spreading_factor: None,
+            start_s: 600.0,
+            interval_ms: 1000.0,
+        }
+    }
+}
+
+impl ClassCConfig {
+    /// Check the parameters, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.downlink_interval_s.is_finite() || self.downlink_interval_s <= 0.0 {
+            return Err(format!("downlink_interval_s must be positive, got {}", self.downlink_interval_s));
+        }
+        let multicast = &self.multicast;
+        if multicast.spreading_factor.is_some_and(|sf| !(7..=12).contains(&sf)) {
+            return Err(format!("multicast.spreading_factor must be within 7 to 12, got {:?}", multicast.spreading_factor));
+        }
+        if !multicast.start_s.is_finite() || multicast.start_s < 0.0 {
+            return Err(format!("multicast.start_s must not be negative, got {}", multicast.start_s));
+        }
+        if !multicast.interval_ms.is_finite() || multicast.interval_ms < 0.0 {
+            return Err(format!("multicast.interval_ms must not be negative, got {}", multicast.interval_ms));
+        }
+        Ok(())
+    }
+}
+
+/// Class C downlinks and multicast fragments, shared by the network server and the engine
+#[derive(Debug, Clone)]
+pub struct ClassC {
+    inner: Arc<Mutex<ClassCState>>,
+    config: ClassCConfig,               // Unicast traffic and multicast session
+    region: Region,                     // Region giving the RX2 channel and data rate
+    downlink: DownlinkConfig,           // Payload and power of the unicast downlinks
+}
+
+#[derive(Debug)]
+struct ClassCState {
+    routes: BTreeMap<u32, (usize, Packet)>, // Gateway index and first delivered copy of the last uplink of each node
+    requested: u64,                     // Unicast downlinks the application asked for
+    received: u64,                      // Unicast downlinks heard by their node
+    latencies_ms: Samples,              // From the request to the end of each unicast downlink
+    fragments_sent: u32,                // Fragments of the session sent so far
+    transmissions: u64,                 // Transmissions of these fragments, one per gateway
+    session: Option<(DateTime<Local>, DateTime<Local>)>, // Start of the first fragment and end of the last one
+    fragments: BTreeMap<u32, BTreeSet<u32>>, // Fragments each member of the group heard
+    missed: u64,                        // Fragments members in range missed while transmitting an uplink
+    uplinks: BTreeMap<u32, (DateTime<Local>, DateTime<Local>)>, // Start and end of the last uplink of each node, deaf meanwhile
+    blocked: u64,                       // Uplinks lost while their gateway was transmitting, from the session start to its last fragment end so far
+    blocked_after: u64,                 // Those lost since the last fragment end so far, in the session if another fragment follows
+    rng: ChaCha8Rng,                    // Class C stream, draws the times of the unicast downlinks
+}
+
+impl ClassC {
+    /// Class C of a region, unicast downlinks with the payload and power of `downlink`, its random stream derives from `seed`
+    pub fn new(config: ClassCConfig, region: Region, downlink: DownlinkConfig, seed: u64) -> Self {
+        let state = ClassCState {
+            routes: BTreeMap::new(),
+            requested: 0,
+            received: 0,
+            latencies_ms: Samples::default(),
+            fragments_sent: 0,
+            transmissions: 0,
+            session: None,
+            fragments: BTreeMap::new(),
+            missed: 0,
+            uplinks: BTreeMap::new(),
+            blocked: 0,
+            blocked_after: 0,
+            rng: stream_rng(seed, RngStream::ClassC),
+        };
+        ClassC { inner: Arc::new(Mutex::new(state)), config, region, downlink }
+    }
+
+    /// Whether the nodes listen continuously
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// The gateway at `gateway` delivered the first copy of an uplink, returns whether its node was unknown so far
+    ///
+    /// The node joins the multicast group with this uplink, the application starts sending it downlinks
+    pub fn route(&self, gateway: usize, packet: &Packet) -> bool {
+        let mut state = self.inner.lock().unwrap();
+        state.fragments.entry(packet.node_id).or_default();
+        state.routes.insert(packet.node_id, (gateway, packet.clone())).is_none()
+    }
+
+    /// Instant of the next unicast downlink the application sends a node, after one at `now`
+    pub fn next_request(&self, now: DateTime<Local>) -> DateTime<Local> {
+        let u: f64 = self.inner.lock().unwrap().rng.gen();
+        let interval_s = -self.config.downlink_interval_s * (1.0 - u).ln();
+        now + Duration::microseconds((interval_s * 1e6).round() as i64)
+    }
+
+    /// The application asks for a unicast downlink to a node, returns the gateway to send it through and the downlink,
+    /// to be moved to the instant the gateway can send it
+    pub fn request(&self, node_id: u32, now: DateTime<Local>) -> (usize, Downlink) {
+        let mut state = self.inner.lock().unwrap();
+        state.requested += 1;
+        let (gateway, uplink) = state.routes.get(&node_id).expect("Class C downlinks go to nodes with a route");
+        let (frequency, data_rate) = self.region.rx2();
+        (*gateway, Downlink::to(uplink, ReceiveWindow::Rxc, frequency, data_rate, now, &self.downlink))
+    }
+
+    /// A unicast downlink asked for at `requested_at` is to be sent
+    pub fn scheduled(&self, downlink: &Downlink, requested_at: DateTime<Local>) {
+        let latency_ms = (downlink.end() - requested_at).num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0;
+        self.inner.lock().unwrap().latencies_ms.push(latency_ms);
+    }
+
+    /// A node heard a unicast downlink
+    pub fn received(&self) {
+        self.inner.lock().unwrap().received += 1;
+    }
+
+    /// Start of the multicast session after a run starting at `start`, `None` without a session
+    pub fn session_start(&self, start: DateTime<Local>) -> Option<DateTime<Local>> {
+        (self.config.multicast.fragments > 0)
+            .then(|| start + Duration::microseconds((self.config.multicast.start_s * 1e6).round() as i64))
+    }
+
+    /// Due time of the fragment after one sent at `sent`, `None` once every fragment is sent
+    pub fn next_fragment(&self, index: u32, sent: DateTime<Local>) -> Option<DateTime<Local>> {
+        (index + 1 < self.config.multicast.fragments)
+            .then(|| sent + Duration::microseconds((self.config.multicast.interval_ms * 1000.0).round() as i64))
+    }
+
+    /// Gateways the fragments are sent through, those routing to a member of the group
+    pub fn gateways(&self) -> BTreeSet<usize> {
+        self.inner.lock().unwrap().routes.values().map(|(gateway, _)| *gateway).collect()
+    }
+
+    /// Downlinks of a fragment starting at `start`, one for each member of the group
+    pub fn fragment(&self, start: DateTime<Local>) -> Vec<Downlink> {
+        let multicast = &self.config.multicast;
+        let (frequency, rx2_rate) = self.region.rx2();
+        let data_rate = DataRate {
+            spreading_factor: multicast.spreading_factor.unwrap_or(rx2_rate.spreading_factor),
+            bandwidth: rx2_rate.bandwidth,
+        };
+        let config = DownlinkConfig { payload_length: multicast.payload_length, ..self.downlink.clone() };
+        self.inner.lock().unwrap()
+            .routes
+            .values()
+            .map(|(_, uplink)| Downlink::to(uplink, ReceiveWindow::Rxc, frequency, data_rate, start, &config))
+            .collect()
+    }
+
+    /// A gateway sends fragment `index` from `start` to `end`, told no later than `start`
+    pub fn fragment_sent(&self, index: u32, start: DateTime<Local>, end: DateTime<Local>) {
+        let mut state = self.inner.lock().unwrap();
+        state.fragments_sent = state.fragments_sent.max(index + 1);
+        state.transmissions += 1;
+        state.session = Some(match state.session {
+            Some((first, last)) => (first.min(start), last.max(end)),
+            None => (start, end),
+        });
+        // The uplinks lost since the previous fragment end fall within the session extended by this fragment
+        state.blocked += std::mem::take(&mut state.blocked_after);
+    }
+
+    /// A member of the group is in range of fragment `index` sent from `start` to `end`,
+    /// returns whether it heard it, which it does not while it transmits an uplink
+    pub fn fragment_received(&self, node_id: u32, index: u32, start: DateTime<Local>, end: DateTime<Local>) -> bool {
+        let mut state = self.inner.lock().unwrap();
+        let transmitting = state.uplinks
+            .get(&node_id)
+            .is_some_and(|&(uplink_start, uplink_end)| uplink_start < end && start < uplink_end);
+        if transmitting {
+            state.missed += 1;
+        } else {
+            state.fragments.entry(node_id).or_default().insert(index);
+        }
+        !transmitting
+    }
+
+    /// A node transmits an uplink from `start` to `end`, it hears no fragment meanwhile
+    pub fn uplink_sent(&self, node_id: u32, start: DateTime<Local>, end: DateTime<Local>) {
+        self.inner.lock().unwrap().uplinks.insert(node_id, (start, end));
+    }
+
+    /// A gateway lost an uplink ending at `time` while it was transmitting, told in order of `time`
+    pub fn uplink_blocked(&self, time: DateTime<Local>) {
+        let mut state = self.inner.lock().unwrap();
+        match state.session {
+            Some((first, last)) if time > first && time <= last => state.blocked += 1,
+            Some((first, _)) if time > first => state.blocked_after += 1,
+            _ => {}
+        }
+    }
+
+    /// Class C statistics with the fragments each member of the group heard, if a fragment was sent
+    pub fn report(&self) -> ClassCReport {
+        let state = self.inner.lock().unwrap();
+        let devices: Vec<DeviceFragments> = state.fragments
+            .iter()
+            .filter(|_| state.fragments_sent > 0)
+            .map(|(&node_id, fragments)| DeviceFragments { node_id, received: fragments.len() as u32 })
+            .collect();
+        let counts: Vec<f64> = devices.iter().map(|device| f64::from(device.received)).collect();
+        ClassCReport {
+            requested: state.requested,
+            received: state.received,
+            delivery_ratio: ratio(state.received, state.requested),
+            latency_ms: state.latencies_ms.summary(),
+            fragments: state.fragments_sent,
+            transmissions: state.transmissions,
+            session_ms: state.session.map_or(0.0, |(first, last)| (last - first).num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0),
+            missed: state.missed,
+            blocked: state.blocked,
+            fragments_per_device: Summary::from_samples(&counts),
+            min_fragments: devices.iter().map(|device| device.received).min().unwrap_or(0),
+            complete: devices.iter().filter(|device| device.received == state.fragments_sent).count() as u64,
+            devices,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::phy::PhyParams;
+
+    fn class_c(fragments: u32) -> ClassC {
+        let multicast = MulticastConfig { fragments, ..MulticastConfig::default() };
+        ClassC::new(ClassCConfig { enabled: true, multicast, ..ClassCConfig::default() }, Region::Eu868, DownlinkConfig::default(), 1)
+    }
+
+    fn uplink(node_id: u32) -> Packet {
+        Packet { node_id, ..Packet::from_phy(node_id, PhyParams::default(), Local::now()) }
+    }
+
+    #[test]
+    fn member_misses_fragments_while_transmitting() {
+        let class_c = class_c(2);
+        class_c.route(0, &uplink(1));
+        class_c.route(0, &uplink(2));
+        let start = Local::now();
+        let end = start + Duration::milliseconds(300);
+        class_c.fragment_sent(0, start, end);
+        class_c.uplink_sent(1, start + Duration::milliseconds(200), start + Duration::milliseconds(400));
+        class_c.uplink_sent(2, start - Duration::milliseconds(200), start);
+
+        assert!(!class_c.fragment_received(1, 0, start, end));
+        assert!(class_c.fragment_received(2, 0, start, end));
+        let report = class_c.report();
+        assert_eq!(report.missed, 1);
+        assert_eq!(report.devices.iter().map(|device| device.received).collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn only_uplinks_lost_during_the_session_are_counted() {
+        let class_c = class_c(2);
+        let start = Local::now();
+        let at = |ms| start + Duration::milliseconds(ms);
+        class_c.uplink_blocked(at(0));
+        class_c.fragment_sent(0, at(100), at(400));
+        class_c.uplink_blocked(at(100));
+        class_c.uplink_blocked(at(300));
+        class_c.uplink_blocked(at(800));
+        class_c.fragment_sent(1, at(1400), at(1700));
+        class_c.uplink_blocked(at(1700));
+        class_c.uplink_blocked(at(2000));
+
+        // Within the session from 100 to 1700 ms, the start excluded
+        assert_eq!(class_c.report().blocked, 3);
+    }
+
+    #[test]
+    fn next_fragment_is_due_an_interval_after_the_send() {
+        let class_c = class_c(2);
+        let sent = Local::now();
+        assert_eq!(class_c.next_fragment(0, sent), Some(sent + Duration::milliseconds(1000)));
+        assert_eq!(class_c.next_fragment(1, sent), None);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,51 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use chrono::{DateTime, Local};
+use std::sync::{Arc, Mutex};
+
+/// Source of the current time seen by the gateway
+#[derive(Debug, Clone)]
+pub enum Clock {
+    Wall,                                   // Wall-clock time, used by the real-time mode
+    Virtual(Arc<Mutex<DateTime<Local>>>),   // Simulated time, advanced by the event engine
+}
+
+impl Clock {
+    /// Create a virtual clock starting at the given instant
+    pub fn virtual_at(start: DateTime<Local>) -> Self {
+        Clock::Virtual(Arc::new(Mutex::new(start)))
+    }
+
+    /// Get the current time of the clock
+    pub fn now(&self) -> DateTime<Local> {
+        match self {
+            Clock::Wall => Local::now(),
+            Clock::Virtual(time) => *time.lock().unwrap(),
+        }
+    }
+
+    /// Move a virtual clock forward to the given instant, the wall clock cannot be moved
+    pub fn advance_to(&self, instant: DateTime<Local>) {
+        if let Clock::Virtual(time) = self {
+            let mut time = time.lock().unwrap();
+            if instant > *time {
+                *time = instant;
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,176 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::duty_cycle::{DutyCycle, DutyCyclePolicy};
+use crate::models::packet::Packet;
+use crate::models::rng::{stream_rng, RngStream};
+
+use log::{info, warn};
+use chrono::{DateTime, Local, Duration};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+/// Parameters of the confirmed uplinks the nodes retransmit until they are acknowledged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfirmedConfig {
+    pub enabled: bool,              // Whether every uplink asks the network server for an acknowledgement
+    pub nb_trans: u32,              // Transmissions of a frame before its node gives up
+    pub retransmit_timeout_ms: [f64; 2], // Inclusive range of the delay from the end of RX2 to a retransmission
+}
+
+impl Default for ConfirmedConfig {
+    fn default() -> Self {
+        ConfirmedConfig {
+            enabled: false,
+            nb_trans: 8,
+            retransmit_timeout_ms: [1000.0, 3000.0],
+        }
+    }
+}
+
+impl ConfirmedConfig {
+    /// Check the parameters, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=15).contains(&self.nb_trans) {
+            return Err(format!("nb_trans must be within 1 to 15, got {}", self.nb_trans));
+        }
+        let [min, max] = self.retransmit_timeout_ms;
+        if !min.is_finite() || !max.is_finite() || min < 0.0 || min > max {
+            return Err(format!("retransmit_timeout_ms must be an increasing range of non-negative delays, got {:?}", self.retransmit_timeout_ms));
+        }
+        Ok(())
+    }
+}
+
+/// Nodes retransmitting the confirmed uplinks no acknowledgement answered, held to their duty cycle
+#[derive(Debug)]
+pub struct Retransmitter {
+    config: ConfirmedConfig,            // Transmissions of a frame and delay before each retransmission
+    channels: HashMap<u32, Vec<u32>>,   // Channel plan of each known node
+    duty_cycle: DutyCycle,              // Airtime used by each node in each sub-band
+    policy: DutyCyclePolicy,            // What to do with a retransmission the duty cycle forbids
+    rng: ChaCha8Rng,                    // Confirmation stream, draws the delays and channels
+}
+
+impl Retransmitter {
+    /// Retransmitter of the nodes with the given channel plans, its random stream derives from `seed`
+    pub fn new(config: ConfirmedConfig, channels: HashMap<u32, Vec<u32>>, duty_cycle: DutyCycle, policy: DutyCyclePolicy, seed: u64) -> Self {
+        Retransmitter {
+            config,
+            channels,
+            duty_cycle,
+            policy,
+            rng: stream_rng(seed, RngStream::Confirmation),
+        }
+    }
+
+    /// Next transmission of an uplink whose receive windows closed at `now` without an acknowledgement,
+    /// `None` once its node has sent it `nb_trans` times or the duty cycle drops it
+    ///
+    /// The retransmission hops to a random channel of the plan of its node, or to the one the duty cycle frees first
+    pub fn retransmit(&mut self, packet: &Packet, now: DateTime<Local>) -> Option<Packet> {
+        if packet.attempt + 1 >= self.config.nb_trans {
+            warn!("\u{1F4ED}: Packet {} (frame {} of node {}) is given up unacknowledged after {} transmissions",
+                packet.packet_id, packet.frame_counter, packet.node_id, packet.attempt + 1);
+            return None;
+        }
+
+        let [min, max] = self.config.retransmit_timeout_ms;
+        let delay_ms = self.rng.gen_range(min..=max);
+        let due = now + Duration::microseconds((delay_ms * 1000.0).round() as i64);
+        let plan = self.channels.get(&packet.node_id).map_or(&[][..], Vec::as_slice);
+        let drawn = if plan.is_empty() { packet.phy.frequency } else { plan[self.rng.gen_range(0..plan.len())] };
+        let (frequency, available_at) = std::iter::once(drawn)
+            .chain(plan.iter().copied())
+            .map(|frequency| (frequency, self.duty_cycle.available_at(packet.node_id, frequency, due)))
+            .min_by_key(|&(_, available_at)| available_at)
+            .unwrap();
+
+        if available_at > due {
+            if self.policy == DutyCyclePolicy::Drop {
+                warn!("\u{23F3}: Retransmission of packet {} of node {} is dropped, the duty cycle forbids it to transmit for {} ms",
+                    packet.packet_id, packet.node_id, (available_at - due).num_milliseconds());
+                self.duty_cycle.blocked(frequency);
+                return None;
+            }
+            self.duty_cycle.deferred(frequency, available_at - due);
+        }
+
+        let mut resend = packet.clone();
+        resend.attempt += 1;
+        resend.arrival_time = available_at.max(due);
+        resend.phy.frequency = frequency;
+        self.duty_cycle.transmit(resend.node_id, frequency, resend.arrival_time, resend.phy.time_on_air());
+        info!("\u{1F501}: Packet {} (frame {} of node {}) is unacknowledged, transmission {} in {} ms",
+            packet.packet_id, packet.frame_counter, packet.node_id, resend.attempt + 1, (resend.arrival_time - now).num_milliseconds());
+        Some(resend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::phy::PhyParams;
+
+    const PLAN: [u32; 3] = [868_100_000, 868_300_000, 868_500_000];
+
+    /// Retransmitter of node 1 on the three default EU868 channels, without duty-cycle limits
+    fn retransmitter(nb_trans: u32) -> Retransmitter {
+        let config = ConfirmedConfig { enabled: true, nb_trans, ..ConfirmedConfig::default() };
+        let channels = HashMap::from([(1, PLAN.to_vec())]);
+        Retransmitter::new(config, channels, DutyCycle::new(&[]), DutyCyclePolicy::Defer, 1)
+    }
+
+    /// Every transmission of a frame whose acknowledgement is always lost
+    fn transmissions(retransmitter: &mut Retransmitter) -> Vec<Packet> {
+        let phy = PhyParams { frequency: PLAN[0], ..PhyParams::default() };
+        let first = Packet { node_id: 1, frame_counter: 7, ..Packet::from_phy(1, phy, Local::now()) };
+        std::iter::successors(Some(first), |packet| {
+            // The receive windows close two seconds after each transmission starts
+            retransmitter.retransmit(packet, packet.arrival_time + Duration::seconds(2))
+        }).collect()
+    }
+
+    #[test]
+    fn lost_acknowledgement_gives_nb_trans_transmissions_of_the_same_frame() {
+        let sent = transmissions(&mut retransmitter(4));
+        assert_eq!(sent.len(), 4);
+        for (attempt, packet) in sent.iter().enumerate() {
+            assert_eq!(packet.attempt, attempt as u32);
+            assert_eq!(packet.frame_counter, 7);
+            assert_eq!(packet.packet_id, 1);
+            assert!(PLAN.contains(&packet.phy.frequency));
+        }
+    }
+
+    #[test]
+    fn retransmissions_wait_for_the_retransmit_timeout() {
+        let sent = transmissions(&mut retransmitter(8));
+        for pair in sent.windows(2) {
+            let delay = pair[1].arrival_time - (pair[0].arrival_time + Duration::seconds(2));
+            assert!(delay >= Duration::seconds(1) && delay <= Duration::seconds(3), "delay {:?}", delay);
+        }
+    }
+
+    #[test]
+    fn single_transmission_is_never_repeated() {
+        assert_eq!(transmissions(&mut retransmitter(1)).len(), 1);
+    }
+}
\ No newline at end of file
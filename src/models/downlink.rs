@@ -0,0 +1,223 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::models::class_b::{beacon_period, beacon_start};
+use crate::models::packet::Packet;
+use crate::models::phy::PhyParams;
+use crate::models::region::{DataRate, Region};
+
+use chrono::{DateTime, Local, Duration};
+use serde::{Serialize, Deserialize};
+
+/// Parameters of the Class A downlinks of the network server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DownlinkConfig {
+    pub enabled: bool,              // Whether the nodes open receive windows the network server answers in
+    pub probability: f64,           // Fraction of the uplinks the application answers, MAC commands are always sent
+    pub payload_length: u8,         // PHY payload of a downlink in bytes
+    pub tx_power_dbm: f64,          // Transmission power of the gateways
+    pub rx1_delay_ms: i64,          // From the end of an uplink to the first receive window, the second opens a second later
+}
+
+impl Default for DownlinkConfig {
+    fn default() -> Self {
+        DownlinkConfig {
+            enabled: false,
+            probability: 0.0,
+            payload_length: 13,
+            tx_power_dbm: 14.0,
+            rx1_delay_ms: 1000,
+        }
+    }
+}
+
+impl DownlinkConfig {
+    /// Check the parameters, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.probability) {
+            return Err(format!("probability must be within 0 to 1, got {}", self.probability));
+        }
+        if !self.tx_power_dbm.is_finite() {
+            return Err(String::from("tx_power_dbm must be a finite number"));
+        }
+        if self.rx1_delay_ms <= 0 {
+            return Err(format!("rx1_delay_ms must be positive, got {}", self.rx1_delay_ms));
+        }
+        Ok(())
+    }
+}
+
+/// Receive window a node opens, after each uplink in Class A, at scheduled instants in Class B or continuously in Class C
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiveWindow {
+    Rx1,                     // On the channel and data rate of the uplink, RX1 delay after it ends
+    Rx2,                     // On the fixed channel and data rate of the region, a second later
+    PingSlot,                // Class B ping slot, at an offset from the beacon depending on the node
+    Rxc,                     // Class C window, open on the channel and data rate of RX2 whenever the node does not transmit
+}
+
+impl ReceiveWindow {
+    /// Name of the window in logs and exported files
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReceiveWindow::Rx1 => "rx1",
+            ReceiveWindow::Rx2 => "rx2",
+            ReceiveWindow::PingSlot => "ping-slot",
+            ReceiveWindow::Rxc => "rxc",
+        }
+    }
+}
+
+/// Why a downlink could be sent in neither receive window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissedDownlink {
+    Late,                    // The uplink reached the network server after the windows opened
+    GatewayBusy,             // The gateway was already transmitting
+    DutyCycle,               // The duty cycle of the gateway forbade it
+}
+
+/// Downlink answering an uplink in one of the receive windows of its node
+#[derive(Debug, Clone)]
+pub struct Downlink {
+    pub node_id: u32,               // Node the downlink is sent to
+    pub frame_counter: u32,         // Frame counter of the uplink it answers
+    pub uplink_id: u32,             // Packet ID of the uplink it answers
+    pub window: ReceiveWindow,      // Receive window it is sent in
+    pub ack: bool,                  // Whether it acknowledges a confirmed uplink
+    pub phy: PhyParams,             // LoRa physical layer parameters of the window
+    pub tx_power_dbm: f64,          // Transmission power of the gateway
+    pub rssi: f64,                  // Power the node receives it at when its position is unknown
+    pub start: DateTime<Local>,     // Start of the transmission, when the window opens
+}
+
+impl Downlink {
+    /// Downlink answering `uplink`, which ended at `uplink_end`, in the given Class A window of a node of `region`
+    pub fn answer(uplink: &Packet, uplink_end: DateTime<Local>, window: ReceiveWindow, config: &DownlinkConfig, region: Region) -> Self {
+        let uplink_rate = DataRate { spreading_factor: uplink.phy.spreading_factor, bandwidth: uplink.phy.bandwidth };
+        let (frequency, data_rate, delay_ms) = match window {
+            ReceiveWindow::Rx1 => {
+                let (frequency, data_rate) = region.rx1(uplink.phy.frequency, uplink_rate);
+                (frequency, data_rate, config.rx1_delay_ms)
+            }
+            ReceiveWindow::Rx2 => {
+                let (frequency, data_rate) = region.rx2();
+                (frequency, data_rate, config.rx1_delay_ms + 1000)
+            }
+            ReceiveWindow::PingSlot | ReceiveWindow::Rxc => panic!("Only the Class A windows are opened by uplinks"),
+        };
+        Downlink::to(uplink, window, frequency, data_rate, uplink_end + Duration::milliseconds(delay_ms), config)
+    }
+
+    /// Downlink to the node of `uplink` starting at `start`, on the given frequency and data rate
+    ///
+    /// Without a known position the node hears the gateway as the gateway heard it, corrected for the power difference
+    pub fn to(uplink: &Packet, window: ReceiveWindow, frequency: u32, data_rate: DataRate, start: DateTime<Local>,
+        config: &DownlinkConfig) -> Self {
+        Downlink {
+            node_id: uplink.node_id,
+            frame_counter: uplink.frame_counter,
+            uplink_id: uplink.packet_id,
+            window,
+            ack: false,
+            phy: PhyParams {
+                frequency,
+                spreading_factor: data_rate.spreading_factor,
+                bandwidth: data_rate.bandwidth,
+                payload_length: config.payload_length,
+                crc: false,
+                ..PhyParams::default()
+            },
+            tx_power_dbm: config.tx_power_dbm,
+            rssi: uplink.rssi + config.tx_power_dbm - uplink.tx_power_dbm.unwrap_or(config.tx_power_dbm),
+            start,
+        }
+    }
+
+    /// End of the transmission
+    pub fn end(&self) -> DateTime<Local> {
+        self.start + self.phy.time_on_air()
+    }
+}
+
+/// Downlinks and beacons a gateway is to transmit, during which it cannot receive on any channel
+#[derive(Debug, Default)]
+pub struct DownlinkScheduler {
+    transmissions: Vec<(DateTime<Local>, DateTime<Local>)>, // Start and end of the scheduled downlinks
+    beacon: Option<(DateTime<Local>, Duration)>, // Start of the first beacon period and time on air of the beacon, when the gateway beacons
+}
+
+impl DownlinkScheduler {
+    /// Scheduler with no downlink scheduled
+    pub fn new() -> Self {
+        DownlinkScheduler::default()
+    }
+
+    /// Scheduler of a gateway sending a beacon lasting `time_on_air` at the start of every beacon period from `epoch`
+    pub fn with_beacons(epoch: DateTime<Local>, time_on_air: Duration) -> Self {
+        DownlinkScheduler { beacon: Some((epoch, time_on_air)), ..DownlinkScheduler::default() }
+    }
+
+    /// Whether the gateway transmits at some point between `start` and `end`
+    pub fn transmitting(&self, start: DateTime<Local>, end: DateTime<Local>) -> bool {
+        self.busy_until(start, end).is_some()
+    }
+
+    /// End of the last transmission of the gateway overlapping `start` to `end`, if any
+    fn busy_until(&self, start: DateTime<Local>, end: DateTime<Local>) -> Option<DateTime<Local>> {
+        let beacon = self.beacon.and_then(|(epoch, time_on_air)| {
+            let last = beacon_period(epoch, end);
+            [last.saturating_sub(1), last]
+                .into_iter()
+                .map(|period| beacon_start(epoch, period))
+                .filter(|&beacon| beacon < end && start < beacon + time_on_air)
+                .map(|beacon| beacon + time_on_air)
+                .max()
+        });
+        self.transmissions
+            .iter()
+            .filter(|&&(tx_start, tx_end)| tx_start < end && start < tx_end)
+            .map(|&(_, tx_end)| tx_end)
+            .chain(beacon)
+            .max()
+    }
+
+    /// Earliest instant from `from` the gateway is free to transmit for `duration`
+    pub fn next_free(&self, from: DateTime<Local>, duration: Duration) -> DateTime<Local> {
+        let mut start = from;
+        while let Some(end) = self.busy_until(start, start + duration) {
+            start = end;
+        }
+        start
+    }
+
+    /// Reserve the gateway for the first of the candidate downlinks it is free for and `clear` allows, if any
+    pub fn schedule(&mut self, candidates: Vec<Downlink>, clear: impl Fn(&Downlink) -> bool) -> Option<Downlink> {
+        let downlink = candidates.into_iter().find(|downlink| !self.transmitting(downlink.start, downlink.end()) && clear(downlink))?;
+        self.reserve(downlink.start, downlink.end());
+        Some(downlink)
+    }
+
+    /// Reserve the gateway for a downlink from `start` to `end`
+    pub fn reserve(&mut self, start: DateTime<Local>, end: DateTime<Local>) {
+        self.transmissions.push((start, end));
+    }
+
+    /// Forget the downlinks ended before `time`
+    pub fn prune(&mut self, time: DateTime<Local>) {
+        self.transmissions.retain(|&(_, end)| end > time);
+    }
+}
\ No newline at end of file
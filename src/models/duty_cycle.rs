@@ -0,0 +1,284 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::models::packet::{Packet, PacketStream};
+use crate::models::region::SubBand;
+use crate::models::statistics::{ratio, DutyCycleReport, Samples, SubBandUsage};
+
+use log::{info, warn};
+use chrono::{DateTime, Local, Duration};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+use std::iter::Peekable;
+use std::sync::{Arc, Mutex};
+
+/// What a node does with an uplink due while the duty cycle forbids it to transmit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DutyCyclePolicy {
+    #[default]
+    Defer,                   // Wait until a sub-band of its channel plan allows it
+    Drop,                    // Give the uplink up
+    Off,                     // Transmit regardless, the duty cycle is not tracked
+}
+
+/// Names of all duty-cycle policies
+pub const DUTY_CYCLE_POLICY_NAMES: [&str; 3] = ["defer", "drop", "off"];
+
+impl DutyCyclePolicy {
+    /// Policy of the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "defer" => Some(DutyCyclePolicy::Defer),
+            "drop" => Some(DutyCyclePolicy::Drop),
+            "off" => Some(DutyCyclePolicy::Off),
+            _ => None,
+        }
+    }
+}
+
+/// Airtime the transmitters have used in the duty-cycle sub-bands, shared by the parts of a run
+#[derive(Debug, Clone, Default)]
+pub struct DutyCycle {
+    inner: Arc<Mutex<DutyCycleState>>,
+}
+
+#[derive(Debug, Default)]
+struct DutyCycleState {
+    sub_bands: Vec<SubBand>,                            // Sub-bands with a duty-cycle limit
+    off_until: HashMap<(u32, usize), DateTime<Local>>,  // End of the off period of each transmitter in each sub-band
+    usage: Vec<SubBandUsage>,                           // Transmissions and airtime of each sub-band
+    deferrals_ms: Samples,                              // How long each deferred transmission waited
+}
+
+impl DutyCycleState {
+    /// Index of the sub-band of a frequency, `None` outside of every sub-band
+    fn sub_band(&self, frequency: u32) -> Option<usize> {
+        self.sub_bands.iter().position(|band| (band.min_frequency..=band.max_frequency).contains(&frequency))
+    }
+}
+
+impl DutyCycle {
+    /// Ledger of the given sub-bands, in which nothing was transmitted yet
+    pub fn new(sub_bands: &[SubBand]) -> Self {
+        let state = DutyCycleState {
+            sub_bands: sub_bands.to_vec(),
+            usage: sub_bands
+                .iter()
+                .map(|band| SubBandUsage {
+                    min_frequency: band.min_frequency,
+                    max_frequency: band.max_frequency,
+                    duty_cycle: band.duty_cycle,
+                    ..SubBandUsage::default()
+                })
+                .collect(),
+            ..DutyCycleState::default()
+        };
+        DutyCycle { inner: Arc::new(Mutex::new(state)) }
+    }
+
+    /// Earliest instant from `time` the transmitter may start a transmission on the frequency
+    pub fn available_at(&self, transmitter: u32, frequency: u32, time: DateTime<Local>) -> DateTime<Local> {
+        let state = self.inner.lock().unwrap();
+        state.sub_band(frequency)
+            .and_then(|band| state.off_until.get(&(transmitter, band)))
+            .map_or(time, |&off_until| off_until.max(time))
+    }
+
+    /// Account a transmission starting at `time`, its sub-band stays off until its airtime is the allowed fraction
+    pub fn transmit(&self, transmitter: u32, frequency: u32, time: DateTime<Local>, time_on_air: Duration) {
+        let mut state = self.inner.lock().unwrap();
+        let Some(band) = state.sub_band(frequency) else { return };
+
+        let airtime_us = time_on_air.num_microseconds().unwrap_or(i64::MAX) as f64;
+        let period = Duration::microseconds((airtime_us / state.sub_bands[band].duty_cycle).round() as i64);
+        state.off_until.insert((transmitter, band), time + period);
+        state.usage[band].transmissions += 1;
+        state.usage[band].airtime_ms += airtime_us / 1000.0;
+    }
+
+    /// Count a transmission on the frequency that waited `delay` for the duty cycle
+    pub fn deferred(&self, frequency: u32, delay: Duration) {
+        let mut state = self.inner.lock().unwrap();
+        if let Some(band) = state.sub_band(frequency) {
+            state.usage[band].deferred += 1;
+        }
+        state.deferrals_ms.push(delay.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0);
+    }
+
+    /// Count a transmission on the frequency given up because of the duty cycle
+    pub fn blocked(&self, frequency: u32) {
+        let mut state = self.inner.lock().unwrap();
+        if let Some(band) = state.sub_band(frequency) {
+            state.usage[band].blocked += 1;
+        }
+    }
+
+    /// Deferred and blocked transmissions, with the usage of every sub-band
+    pub fn report(&self) -> DutyCycleReport {
+        let state = self.inner.lock().unwrap();
+        let transmissions: u64 = state.usage.iter().map(|usage| usage.transmissions).sum();
+        let blocked: u64 = state.usage.iter().map(|usage| usage.blocked).sum();
+        let deferral_ms = state.deferrals_ms.summary();
+        let deferred = deferral_ms.count as u64;
+        DutyCycleReport {
+            deferred,
+            blocked,
+            deferred_ratio: ratio(deferred, transmissions + blocked),
+            deferral_ms,
+            sub_bands: state.usage.clone(),
+        }
+    }
+}
+
+/// Uplinks of the nodes held to the duty cycle of their sub-bands, in the order they are transmitted
+pub struct RegulatedUplinks {
+    packets: Peekable<PacketStream>,                    // Uplinks not considered yet, in arrival order
+    waiting: BTreeMap<(DateTime<Local>, u64), (Packet, DateTime<Local>)>, // Deferred uplinks by transmission time, with the time they were due
+    deferrals: u64,                                     // Uplinks deferred so far, keeps the deferred ones in order
+    channels: HashMap<u32, Vec<u32>>,                   // Channel plan of each known node
+    duty_cycle: DutyCycle,                              // Airtime used by each node in each sub-band
+    policy: DutyCyclePolicy,                            // What to do with an uplink the duty cycle forbids
+}
+
+impl RegulatedUplinks {
+    /// Regulate `packets`, the uplinks of nodes with a known channel plan may move to another of its channels
+    pub fn new(packets: PacketStream, channels: HashMap<u32, Vec<u32>>, duty_cycle: DutyCycle, policy: DutyCyclePolicy) -> Self {
+        RegulatedUplinks {
+            packets: packets.peekable(),
+            waiting: BTreeMap::new(),
+            deferrals: 0,
+            channels,
+            duty_cycle,
+            policy,
+        }
+    }
+
+    /// Channel the uplink may go on first with the instant it may, its own channel when it may go at once
+    ///
+    /// The other channels of the plan are tried from one depending on the packet, to spread the uplinks over them
+    fn clearance(&self, packet: &Packet) -> (u32, DateTime<Local>) {
+        let time = packet.arrival_time;
+        let own = packet.phy.frequency;
+        let mut clearance = (own, self.duty_cycle.available_at(packet.node_id, own, time));
+        if clearance.1 <= time {
+            return clearance;
+        }
+
+        if let Some(plan) = self.channels.get(&packet.node_id) {
+            let first = packet.packet_id as usize % plan.len().max(1);
+            for &frequency in plan.iter().cycle().skip(first).take(plan.len()) {
+                let available_at = self.duty_cycle.available_at(packet.node_id, frequency, time);
+                if available_at < clearance.1 {
+                    clearance = (frequency, available_at);
+                }
+            }
+        }
+        clearance
+    }
+}
+
+impl Iterator for RegulatedUplinks {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        loop {
+            let deferred_first = match (self.waiting.first_key_value(), self.packets.peek()) {
+                (Some(((time, _), _)), Some(packet)) => *time <= packet.arrival_time,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            let (mut packet, due) = if deferred_first {
+                self.waiting.pop_first().unwrap().1
+            } else {
+                let packet = self.packets.next()?;
+                let due = packet.arrival_time;
+                (packet, due)
+            };
+
+            let (frequency, available_at) = self.clearance(&packet);
+            if available_at <= packet.arrival_time {
+                packet.phy.frequency = frequency;
+                if packet.arrival_time > due {
+                    self.duty_cycle.deferred(frequency, packet.arrival_time - due);
+                }
+                self.duty_cycle.transmit(packet.node_id, frequency, packet.arrival_time, packet.phy.time_on_air());
+                return Some(packet);
+            }
+
+            match self.policy {
+                DutyCyclePolicy::Drop => {
+                    warn!("\u{23F3}: Packet {} of node {} is dropped, the duty cycle forbids it to transmit for {} ms",
+                        packet.packet_id, packet.node_id, (available_at - packet.arrival_time).num_milliseconds());
+                    self.duty_cycle.blocked(packet.phy.frequency);
+                }
+                DutyCyclePolicy::Defer | DutyCyclePolicy::Off => {
+                    info!("\u{23F3}: Packet {} of node {} waits {} ms for the duty cycle",
+                        packet.packet_id, packet.node_id, (available_at - packet.arrival_time).num_milliseconds());
+                    packet.arrival_time = available_at;
+                    self.waiting.insert((available_at, self.deferrals), (packet, due));
+                    self.deferrals += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::phy::PhyParams;
+    use crate::models::region::Region;
+
+    fn uplink(packet_id: u32, arrival_time: DateTime<Local>) -> Packet {
+        Packet { node_id: 1, ..Packet::from_phy(packet_id, PhyParams::default(), arrival_time) }
+    }
+
+    fn regulated(policy: DutyCyclePolicy, packets: Vec<Packet>) -> (Vec<Packet>, DutyCycleReport) {
+        let duty_cycle = DutyCycle::new(Region::Eu868.sub_bands());
+        let uplinks = RegulatedUplinks::new(Box::new(packets.into_iter()), HashMap::new(), duty_cycle.clone(), policy);
+        (uplinks.collect(), duty_cycle.report())
+    }
+
+    #[test]
+    fn off_time_is_airtime_over_duty_cycle() {
+        let duty_cycle = DutyCycle::new(Region::Eu868.sub_bands());
+        let start = Local::now();
+        duty_cycle.transmit(1, 868_100_000, start, Duration::microseconds(56_576));
+
+        // 1% in 868.0 to 868.6 MHz, the 10% sub-band and the other nodes are not held
+        assert_eq!(duty_cycle.available_at(1, 868_300_000, start), start + Duration::microseconds(5_657_600));
+        assert_eq!(duty_cycle.available_at(1, 869_525_000, start), start);
+        assert_eq!(duty_cycle.available_at(2, 868_100_000, start), start);
+    }
+
+    #[test]
+    fn uplink_during_off_time_is_deferred() {
+        let start = Local::now();
+        let (uplinks, report) = regulated(DutyCyclePolicy::Defer, vec![uplink(1, start), uplink(2, start + Duration::seconds(1))]);
+        let off_until = start + PhyParams::default().time_on_air() * 100;
+        assert_eq!(uplinks.iter().map(|packet| packet.arrival_time).collect::<Vec<_>>(), vec![start, off_until]);
+        assert_eq!(report.deferred, 1);
+    }
+
+    #[test]
+    fn uplink_during_off_time_is_dropped() {
+        let start = Local::now();
+        let (uplinks, report) = regulated(DutyCyclePolicy::Drop, vec![uplink(1, start), uplink(2, start + Duration::seconds(1))]);
+        assert_eq!(uplinks.len(), 1);
+        assert_eq!(report.blocked, 1);
+    }
+}
\ No newline at end of file
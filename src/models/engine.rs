@@ -0,0 +1,566 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::models::class_b::{beacon_period, beacon_start, ClassB};
+use crate::models::class_c::ClassC;
+use crate::models::confirmed::Retransmitter;
+use crate::models::downlink::{Downlink, DownlinkScheduler, MissedDownlink, ReceiveWindow};
+use crate::models::event::{EventKind, EventQueue};
+use crate::models::gateway::*;
+use crate::models::interference::{Interference, ReceptionOutcome};
+use crate::models::network::{Coverage, NetworkServer};
+use crate::models::packet::{Packet, PacketStream};
+use crate::models::trace::TraceEvent;
+
+use log::{info, warn};
+use chrono::{DateTime, Local};
+
+/// Gateway driven by the engine with the state of its receiver
+struct GatewayState {
+    gateway: Gateway,           // Gateway whose queues are driven
+    channel_busy: Vec<bool>,    // Whether each channel is currently processing a packet
+    interference: Interference, // Transmissions the gateway hears, decides collisions and capture
+    downlinks: DownlinkScheduler, // Downlinks the gateway is to transmit, deaf to uplinks meanwhile
+}
+
+/// Discrete-event engine driving the pending and channel queues of the gateways on a virtual clock
+pub struct EventEngine {
+    gateways: Vec<GatewayState>,    // Gateways sharing the virtual clock, indexed by the events
+    events: EventQueue,             // Future events ordered by simulated time
+    server: NetworkServer,          // Network server the delivered packets are forwarded to
+    coverage: Coverage,             // Which gateways hear each node and at which power
+    arrivals: PacketStream,         // Packets not yet scheduled, in arrival order
+    retransmitter: Option<Retransmitter>, // Nodes retransmitting their unacknowledged uplinks, when they are confirmed
+    class_b: Option<ClassB>,        // Beacons and ping slots, when the nodes are in Class B
+    class_c: Option<ClassC>,        // Unicast and multicast downlinks, when the nodes are in Class C
+    arrivals_done: bool,            // Whether every packet was scheduled, periodic events stop then
+    epoch: DateTime<Local>,         // Start of the run, beacon periods are counted from it
+}
+
+impl EventEngine {
+    /// Create a new event engine for the given gateways, which must share a virtual clock
+    pub fn new(gateways: Vec<Gateway>, server: NetworkServer, coverage: Coverage, retransmitter: Option<Retransmitter>,
+        class_b: Option<ClassB>, class_c: Option<ClassC>) -> Self {
+        let epoch = gateways[0].clock.now();
+        let beacon = class_b.as_ref().map(|class_b| class_b.beacon(0).time_on_air());
+        let gateways = gateways
+            .into_iter()
+            .map(|gateway| {
+                let channel_count = gateway.channels.lock().unwrap().len();
+                let interference = Interference::new(gateway.capture);
+                GatewayState {
+                    gateway,
+                    channel_busy: vec![false; channel_count],
+                    interference,
+                    downlinks: beacon.map_or_else(DownlinkScheduler::new, |time_on_air| DownlinkScheduler::with_beacons(epoch, time_on_air)),
+                }
+            })
+            .collect();
+        EventEngine {
+            gateways,
+            events: EventQueue::new(),
+            server,
+            coverage,
+            arrivals: Box::new(std::iter::empty()),
+            retransmitter,
+            class_b,
+            class_c,
+            arrivals_done: false,
+            epoch,
+        }
+    }
+
+    /// Take the packets to simulate, each arrival is scheduled when the previous one happens
+    pub fn schedule_arrivals(&mut self, packets: PacketStream) {
+        self.arrivals = packets;
+        self.schedule_next_arrival();
+    }
+
+    /// Schedule the arrival of the next packet, if any
+    fn schedule_next_arrival(&mut self) {
+        match self.arrivals.next() {
+            Some(packet) => self.events.schedule(packet.arrival_time, EventKind::Arrival(packet)),
+            None => self.arrivals_done = true,
+        }
+    }
+
+    /// Run until no event is left, returns the simulated time of the last state change
+    pub fn run(&mut self) -> DateTime<Local> {
+        let clock = self.gateways[0].gateway.clock.clone();
+        let mut last_activity = clock.now();
+        if self.class_b.is_some() {
+            self.events.schedule(beacon_start(self.epoch, 0), EventKind::Beacon);
+        }
+        if let Some(start) = self.class_c.as_ref().and_then(|class_c| class_c.session_start(last_activity)) {
+            self.events.schedule(start, EventKind::Multicast { index: 0 });
+        }
+
+        while let Some(event) = self.events.pop() {
+            clock.advance_to(event.time);
+            let now = event.time;
+
+            match event.kind {
+                EventKind::Arrival(packet) => {
+                    self.schedule_next_arrival();
+                    self.transmit(packet, now);
+                }
+                EventKind::TransmissionEnd { gateway, packet } => {
+                    let packet_id = packet.packet_id;
+                    let state = &mut self.gateways[gateway];
+                    let mut outcome = state.interference.resolve(packet_id);
+                    if state.downlinks.transmitting(now - packet.phy.time_on_air(), now) {
+                        outcome = ReceptionOutcome::Blocked;
+                    }
+                    state.downlinks.prune(state.interference.earliest_unresolved().unwrap_or(now));
+                    state.gateway.stats.reception(packet_id, now, outcome);
+                    state.gateway.trace.record(now, packet_id, TraceEvent::Reception, None, Some(outcome.name()));
+                    match outcome {
+                        ReceptionOutcome::Received => self.admit(gateway, packet, now),
+                        ReceptionOutcome::Captured => {
+                            info!("Packet {} is captured at gateway {} despite overlapping transmissions", packet_id, state.gateway.id);
+                            self.admit(gateway, packet, now);
+                        }
+                        ReceptionOutcome::Collided => {
+                            warn!("\u{1F4A5}: Packet {} is lost to collision at gateway {}", packet_id, state.gateway.id);
+                            state.gateway.trace.record(now, packet_id, TraceEvent::Outcome, None, Some("collided"));
+                        }
+                        ReceptionOutcome::Blocked => {
+                            warn!("\u{1F4E1}: Packet {} is lost while gateway {} transmits a downlink", packet_id, state.gateway.id);
+                            state.gateway.trace.record(now, packet_id, TraceEvent::Outcome, None, Some("blocked"));
+                            if let Some(class_c) = &self.class_c {
+                                class_c.uplink_blocked(now);
+                            }
+                        }
+                    }
+                }
+                EventKind::Start { gateway, channel } => {
+                    self.start(gateway, channel, now);
+                }
+                EventKind::Finish { gateway, channel, packet } => {
+                    let state = &mut self.gateways[gateway];
+                    info!("\u{1F60A}: Packet {} processed in channel {} of gateway {} (took {} ms)",
+                        packet.packet_id, channel, state.gateway.id, packet.processing_time.num_milliseconds());
+                    state.gateway.trace.record(now, packet.packet_id, TraceEvent::Completion, Some(channel), None);
+                    state.gateway.trace.record(now, packet.packet_id, TraceEvent::Outcome, Some(channel), Some("delivered"));
+                    state.gateway.stats.completion(packet.packet_id, channel, now - packet.processing_time, now);
+                    state.channel_busy[channel] = false;
+                    if self.server.deliver(&packet) {
+                        if let Some(class_b) = self.class_b.as_ref().filter(|class_b| class_b.route(gateway, &packet)) {
+                            self.events.schedule(class_b.next_request(now), EventKind::PingRequest { node_id: packet.node_id });
+                        }
+                        if let Some(class_c) = self.class_c.as_ref().filter(|class_c| class_c.route(gateway, &packet)) {
+                            self.events.schedule(class_c.next_request(now), EventKind::ClassCRequest { node_id: packet.node_id });
+                        }
+                        self.answer(gateway, &packet, now);
+                    } else {
+                        info!("Packet {} from gateway {} is a duplicate of an uplink already delivered", packet.packet_id, state.gateway.id);
+                    }
+                    self.events.schedule(now, EventKind::Start { gateway, channel });
+                }
+                EventKind::Downlink { gateway, downlink } => {
+                    let state = &self.gateways[gateway];
+                    info!("Gateway {} answers packet {} (frame {} of node {}) in {}",
+                        state.gateway.id, downlink.uplink_id, downlink.frame_counter, downlink.node_id, downlink.window.name());
+                    state.gateway.trace.record(now, downlink.uplink_id, TraceEvent::Downlink, None, Some(downlink.window.name()));
+                    match self.coverage.downlink(&downlink, state.gateway.id, &state.gateway.position) {
+                        Some(_) => self.server.downlink_received(&downlink),
+                        None => warn!("Downlink answering packet {} is below the sensitivity of node {}", downlink.uplink_id, downlink.node_id),
+                    }
+                }
+                EventKind::WindowsClosed(packet) => {
+                    self.windows_closed(packet, now);
+                }
+                EventKind::Retransmission(packet) => {
+                    self.transmit(packet, now);
+                }
+                EventKind::Beacon | EventKind::PingRequest { .. } | EventKind::ClassCRequest { .. } if self.arrivals_done => {
+                    // Beacons and the application stop with the uplink traffic, they would only stretch the run
+                    continue;
+                }
+                EventKind::Beacon => {
+                    self.beacon(now);
+                }
+                EventKind::PingRequest { node_id } => {
+                    self.ping(node_id, now);
+                }
+                EventKind::ClassCRequest { node_id } => {
+                    self.class_c_downlink(node_id, now);
+                }
+                EventKind::Multicast { index } => {
+                    self.multicast(index, now);
+                }
+                EventKind::Fragment { gateway, index, start } => {
+                    self.fragment(gateway, index, start);
+                }
+                EventKind::Timeout { gateway, packet_id } => {
+                    match self.gateways[gateway].gateway.resend_expired_packet(packet_id) {
+                        Some(resend) => self.resend(gateway, resend, now),
+                        None => continue,
+                    }
+                    self.dispatch(gateway, now);
+                }
+                EventKind::Retry { gateway, mut packet } => {
+                    packet.arrival_time = now;
+                    self.admit(gateway, packet, now);
+                }
+            }
+
+            last_activity = now;
+        }
+
+        last_activity
+    }
+
+    /// Start the transmission of a packet at every gateway hearing it, each receives its own copy
+    fn transmit(&mut self, packet: Packet, now: DateTime<Local>) {
+        if let Some(class_c) = &self.class_c {
+            class_c.uplink_sent(packet.node_id, now, now + packet.phy.time_on_air());
+        }
+        let mut heard = false;
+        for (gateway, state) in self.gateways.iter_mut().enumerate() {
+            let Some(link) = self.coverage.link(&packet, state.gateway.id, &state.gateway.position) else { continue };
+
+            let mut copy = packet.clone();
+            copy.rssi = link.rssi;
+            copy.snr = link.snr;
+            copy.uplink_end = Some(now + copy.phy.time_on_air());
+            state.gateway.trace.record(now, copy.packet_id, TraceEvent::Arrival, None, None);
+            state.gateway.stats.arrival(copy.packet_id, copy.node_id, now);
+            state.gateway.stats.link(link.rssi, link.snr);
+            state.interference.start(&copy, now);
+            self.events.schedule(now + copy.phy.time_on_air(), EventKind::TransmissionEnd { gateway, packet: copy });
+            heard = true;
+        }
+
+        if !heard {
+            warn!("Packet {} of node {} is below the sensitivity of every gateway", packet.packet_id, packet.node_id);
+        }
+        self.server.uplink(&packet, heard);
+
+        // The node of a confirmed uplink waits for its acknowledgement until its second receive window closes
+        if self.retransmitter.is_some() {
+            let closed = self.server.downlink(&packet, now + packet.phy.time_on_air(), ReceiveWindow::Rx2).end();
+            self.events.schedule(closed, EventKind::WindowsClosed(packet));
+        }
+    }
+
+    /// Retransmit a confirmed uplink whose receive windows closed without an acknowledgement, unless its node gives up
+    fn windows_closed(&mut self, packet: Packet, now: DateTime<Local>) {
+        let Some(retransmitter) = self.retransmitter.as_mut() else { return };
+        if self.server.is_acknowledged(&packet) {
+            self.server.confirmation(&packet, true);
+            return;
+        }
+        match retransmitter.retransmit(&packet, now) {
+            Some(resend) => self.events.schedule(resend.arrival_time, EventKind::Retransmission(resend)),
+            None => self.server.confirmation(&packet, false),
+        }
+    }
+
+    /// Answer a delivered uplink if the network server wants to, in the first receive window the gateway can transmit in
+    ///
+    /// The uplink is delivered once processed, its time on air after it ends, so an SF12 uplink misses an RX1 a second later
+    fn answer(&mut self, gateway: usize, packet: &Packet, now: DateTime<Local>) {
+        let Some(uplink_end) = packet.uplink_end else { return };
+        if !self.server.wants_downlink(packet) {
+            return;
+        }
+
+        let state = &mut self.gateways[gateway];
+        let mut missed = MissedDownlink::Late;
+        for window in [ReceiveWindow::Rx1, ReceiveWindow::Rx2] {
+            let downlink = self.server.downlink(packet, uplink_end, window);
+            missed = if downlink.start < now {
+                MissedDownlink::Late
+            } else if state.downlinks.transmitting(downlink.start, downlink.end()) {
+                MissedDownlink::GatewayBusy
+            } else if !self.server.clear_to_send(state.gateway.id, &downlink) {
+                MissedDownlink::DutyCycle
+            } else {
+                state.downlinks.reserve(downlink.start, downlink.end());
+                state.gateway.stats.downlink();
+                self.server.downlink_sent(state.gateway.id, &downlink);
+                self.events.schedule(downlink.start, EventKind::Downlink { gateway, downlink });
+                return;
+            };
+        }
+
+        warn!("Packet {} of node {} cannot be answered by gateway {} in any receive window ({:?})", packet.packet_id, packet.node_id, state.gateway.id, missed);
+        self.server.downlink_missed(missed);
+    }
+
+    /// Send the beacon of the period starting now from every gateway and schedule the next one
+    fn beacon(&mut self, now: DateTime<Local>) {
+        let Some(class_b) = &self.class_b else { return };
+        let period = beacon_period(self.epoch, now);
+        let beacon = class_b.beacon(period);
+        info!("\u{1F4E2}: Gateways send beacon {} on {:.1} MHz", period, f64::from(beacon.frequency) / 1e6);
+        for state in &self.gateways {
+            self.server.beacon_sent(state.gateway.id, now, &beacon);
+        }
+        self.events.schedule(beacon_start(self.epoch, period + 1), EventKind::Beacon);
+    }
+
+    /// Schedule a downlink the application sends a Class B node in the first free ping slot of the gateway routing to it
+    fn ping(&mut self, node_id: u32, now: DateTime<Local>) {
+        let Some(class_b) = &self.class_b else { return };
+        self.events.schedule(class_b.next_request(now), EventKind::PingRequest { node_id });
+
+        let (gateway, uplink) = class_b.request(node_id);
+        let state = &mut self.gateways[gateway];
+        let gateway_id = state.gateway.id;
+        let server = &self.server;
+        match state.downlinks.schedule(class_b.ping_slots(&uplink, self.epoch, now), |downlink| server.clear_to_send(gateway_id, downlink)) {
+            Some(downlink) => {
+                info!("Gateway {} schedules a downlink to node {} in the ping slot at {}", gateway_id, node_id, downlink.start.format("%H:%M:%S%.3f"));
+                state.gateway.stats.downlink();
+                server.downlink_sent(gateway_id, &downlink);
+                class_b.scheduled(&downlink, now);
+                self.events.schedule(downlink.start, EventKind::Downlink { gateway, downlink });
+            }
+            None => {
+                warn!("Gateway {} finds no free ping slot of node {}", gateway_id, node_id);
+                class_b.unscheduled();
+            }
+        }
+    }
+
+    /// Send a downlink the application asks for to a Class C node as soon as the gateway routing to it can
+    fn class_c_downlink(&mut self, node_id: u32, now: DateTime<Local>) {
+        let Some(class_c) = self.class_c.clone() else { return };
+        self.events.schedule(class_c.next_request(now), EventKind::ClassCRequest { node_id });
+
+        let (gateway, downlink) = class_c.request(node_id, now);
+        let downlink = self.send(gateway, downlink);
+        info!("Gateway {} sends a downlink to Class C node {} at {}", self.gateways[gateway].gateway.id, node_id, downlink.start.format("%H:%M:%S%.3f"));
+        class_c.scheduled(&downlink, now);
+        self.events.schedule(downlink.start, EventKind::Downlink { gateway, downlink });
+    }
+
+    /// Send a fragment of the multicast session through every gateway routing to a member of the group, each as soon as it can,
+    /// the next fragment is due an interval after the last gateway actually sent this one
+    fn multicast(&mut self, index: u32, due: DateTime<Local>) {
+        let Some(class_c) = self.class_c.clone() else { return };
+        let mut sent = due;
+        match class_c.fragment(due).into_iter().next() {
+            Some(template) => {
+                for gateway in class_c.gateways() {
+                    let fragment = self.send(gateway, template.clone());
+                    class_c.fragment_sent(index, fragment.start, fragment.end());
+                    sent = sent.max(fragment.start);
+                    self.events.schedule(fragment.end(), EventKind::Fragment { gateway, index, start: fragment.start });
+                }
+            }
+            None => warn!("Multicast fragment {} has no group to be sent to, no node was heard yet", index),
+        }
+        if let Some(next) = class_c.next_fragment(index, sent) {
+            self.events.schedule(next, EventKind::Multicast { index: index + 1 });
+        }
+    }
+
+    /// A gateway sent a multicast fragment from `start`, heard by every member of the group in its range not transmitting meanwhile
+    fn fragment(&mut self, gateway: usize, index: u32, start: DateTime<Local>) {
+        let Some(class_c) = &self.class_c else { return };
+        let state = &self.gateways[gateway];
+        let fragment = class_c.fragment(start);
+        let members = fragment.len();
+        let mut heard = 0;
+        for downlink in fragment {
+            if self.coverage.downlink(&downlink, state.gateway.id, &state.gateway.position).is_some()
+                && class_c.fragment_received(downlink.node_id, index, start, downlink.end()) {
+                heard += 1;
+            }
+        }
+        info!("Gateway {} multicasts fragment {}, heard by {} of {} nodes", state.gateway.id, index, heard, members);
+    }
+
+    /// Reserve the gateway for a downlink at the earliest instant from its start the gateway is free and its duty cycle allows
+    fn send(&mut self, gateway: usize, mut downlink: Downlink) -> Downlink {
+        let state = &mut self.gateways[gateway];
+        loop {
+            downlink.start = state.downlinks.next_free(downlink.start, downlink.phy.time_on_air());
+            let clear = self.server.available_at(state.gateway.id, &downlink);
+            if clear <= downlink.start {
+                break;
+            }
+            downlink.start = clear;
+        }
+        state.downlinks.reserve(downlink.start, downlink.end());
+        state.gateway.stats.downlink();
+        self.server.downlink_sent(state.gateway.id, &downlink);
+        downlink
+    }
+
+    /// Offer a received packet to the pending queue and watch its waiting deadline if it is admitted
+    fn admit(&mut self, gateway: usize, packet: Packet, now: DateTime<Local>) {
+        let packet_id = packet.packet_id;
+        let state = &self.gateways[gateway];
+        let deadline = packet.arrival_time + state.gateway.time_threshold;
+        let admitted = {
+            let mut pending_queue = state.gateway.pending_queue.lock().unwrap();
+            state.gateway.admit_pending(&mut pending_queue, packet)
+        };
+        if admitted {
+            self.events.schedule(deadline, EventKind::Timeout { gateway, packet_id });
+            self.dispatch(gateway, now);
+        }
+    }
+
+    /// Schedule the return of a timed-out packet to the pending queue once its backoff has elapsed
+    fn resend(&mut self, gateway: usize, resend: Resend, now: DateTime<Local>) {
+        if let Resend::Retry(packet, delay) = resend {
+            self.events.schedule(now + delay, EventKind::Retry { gateway, packet });
+        }
+    }
+
+    /// Move packets from the pending queue to the channel queues until no more can be allocated
+    fn dispatch(&mut self, gateway: usize, now: DateTime<Local>) {
+        loop {
+            let state = &self.gateways[gateway];
+            match state.gateway.distribute_one_packet() {
+                DistributeStatus::Success(channel) => {
+                    if !state.channel_busy[channel] {
+                        self.events.schedule(now, EventKind::Start { gateway, channel });
+                    }
+                }
+                DistributeStatus::Timeout => {
+                    let resend = state.gateway.resend_packet();
+                    self.resend(gateway, resend, now);
+                }
+                DistributeStatus::Unsupported => {}
+                DistributeStatus::AllChannelsFull | DistributeStatus::EmptyQueue => break,
+            }
+        }
+    }
+
+    /// Start processing the next packet of an idle channel
+    fn start(&mut self, gateway: usize, channel: usize, now: DateTime<Local>) {
+        let state = &mut self.gateways[gateway];
+        if state.channel_busy[channel] {
+            return;
+        }
+
+        let packet = match state.gateway.channels.lock().unwrap()[channel].queue.pop_front() {
+            Some(packet) => packet,
+            None => return,
+        };
+
+        info!("\u{1F600}: Packet {} is processing in channel {} of gateway {}", packet.packet_id, channel, state.gateway.id);
+        state.gateway.trace.record(now, packet.packet_id, TraceEvent::ServiceStart, Some(channel), None);
+        state.gateway.stats.service_start(packet.packet_id, now);
+        state.channel_busy[channel] = true;
+        self.events.schedule(now + packet.processing_time, EventKind::Finish { gateway, channel, packet });
+
+        // A slot was freed in the channel queue
+        self.dispatch(gateway, now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_access::scenario::ScenarioConfig;
+    use crate::models::clock::Clock;
+    use crate::models::network::GatewaySite;
+    use crate::models::node::Position;
+    use crate::models::phy::PhyParams;
+    use crate::models::statistics::{DownlinkReport, DutyCycleReport};
+    use chrono::Duration;
+
+    /// Engine of one gateway whose clock starts at `start`, answering every uplink when `answer` is set
+    fn engine(start: DateTime<Local>, answer: bool) -> EventEngine {
+        let mut scenario = ScenarioConfig::default();
+        scenario.network.downlink.enabled = answer;
+        scenario.network.downlink.probability = 1.0;
+        let server = NetworkServer::from_config(&scenario, 1);
+        let mut gateway = Gateway::from_config(&scenario, &GatewaySite { id: 1, position: Position::default() }, 1, server.clone()).unwrap();
+        gateway.clock = Clock::virtual_at(start);
+        let coverage = Coverage::new(scenario.network.propagation.clone(), Vec::new(), 1);
+        EventEngine::new(vec![gateway], server, coverage, None, None, None)
+    }
+
+    fn uplink(packet_id: u32, spreading_factor: u8, arrival_time: DateTime<Local>) -> Packet {
+        let mut packet = Packet::from_phy(packet_id, PhyParams { spreading_factor, ..PhyParams::default() }, arrival_time);
+        packet.node_id = packet_id;
+        packet.uplink_end = Some(arrival_time + packet.phy.time_on_air());
+        packet
+    }
+
+    fn downlinks(engine: &EventEngine) -> DownlinkReport {
+        engine.server.report(1, Vec::new(), DutyCycleReport::default(), std::time::Duration::ZERO).downlinks
+    }
+
+    #[test]
+    fn answer_takes_rx1_and_falls_back_to_rx2_once_rx1_opened() {
+        let start = Local::now();
+        let mut engine = engine(start, true);
+        let packet = uplink(1, 7, start);
+        let uplink_end = packet.uplink_end.unwrap();
+
+        // RX1 opens a second after the uplink ends and RX2 a second later
+        engine.answer(0, &packet, uplink_end + Duration::milliseconds(500));
+        engine.answer(0, &packet, uplink_end + Duration::milliseconds(1500));
+        engine.answer(0, &packet, uplink_end + Duration::milliseconds(2500));
+        let report = downlinks(&engine);
+        assert_eq!((report.rx1, report.rx2, report.late), (1, 1, 1));
+    }
+
+    #[test]
+    fn busy_gateway_answers_in_rx2_or_not_at_all() {
+        let start = Local::now();
+        let mut engine = engine(start, true);
+        let packet = uplink(1, 7, start);
+        let uplink_end = packet.uplink_end.unwrap();
+        let rx1 = uplink_end + Duration::seconds(1);
+        engine.gateways[0].downlinks.reserve(rx1, rx1 + Duration::milliseconds(100));
+
+        engine.answer(0, &packet, uplink_end);
+        assert_eq!((downlinks(&engine).rx1, downlinks(&engine).rx2), (0, 1));
+
+        // The first answer now holds RX2
+        engine.answer(0, &packet, uplink_end);
+        let report = downlinks(&engine);
+        assert_eq!((report.rx1, report.rx2, report.gateway_busy), (0, 1, 1));
+    }
+
+    #[test]
+    fn processing_for_the_time_on_air_makes_slow_uplinks_miss_rx1() {
+        let start = Local::now();
+        let mut engine = engine(start, true);
+        // SF7 is processed in 56.6 ms, well before RX1, SF12 in 1.3 s, after RX1 opened
+        let packets = vec![uplink(1, 7, start), uplink(2, 12, start + Duration::seconds(10))];
+        engine.schedule_arrivals(Box::new(packets.into_iter()));
+        engine.run();
+
+        let report = downlinks(&engine);
+        assert_eq!((report.rx1, report.rx2), (1, 1));
+    }
+
+    #[test]
+    fn uplink_overlapping_a_downlink_of_the_gateway_is_lost() {
+        let start = Local::now();
+        let mut engine = engine(start, false);
+        let downlink_start = start + Duration::milliseconds(1030);
+        engine.gateways[0].downlinks.reserve(downlink_start, downlink_start + Duration::milliseconds(100));
+        // The SF7 uplinks last 56.6 ms, the first ends before the downlink and the second overlaps it
+        let packets = vec![uplink(1, 7, start), uplink(2, 7, start + Duration::seconds(1))];
+        engine.schedule_arrivals(Box::new(packets.into_iter()));
+        let end = engine.run();
+
+        let report = engine.gateways[0].gateway.stats.report(1, start, end);
+        assert_eq!((report.received, report.blocked, report.delivered), (1, 1, 1));
+    }
+}
\ No newline at end of file
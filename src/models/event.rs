@@ -0,0 +1,135 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::downlink::Downlink;
+use crate::models::packet::Packet;
+
+use chrono::{DateTime, Local};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Kind of a simulation event, `gateway` is the index of the gateway concerned
+#[derive(Debug)]
+pub enum EventKind {
+    Arrival(Packet),                                            // Node starts transmitting a packet
+    TransmissionEnd { gateway: usize, packet: Packet },         // Transmission ends at a gateway hearing it, the packet enters the pending queue unless lost
+    Start { gateway: usize, channel: usize },                   // Channel starts processing its next packet
+    Finish { gateway: usize, channel: usize, packet: Packet },  // Channel finishes processing a packet
+    Timeout { gateway: usize, packet_id: u32 },                 // Waiting deadline of a pending packet is reached
+    Retry { gateway: usize, packet: Packet },                   // Timed-out packet re-enters the pending queue after its backoff
+    Downlink { gateway: usize, downlink: Downlink },            // Gateway starts transmitting a downlink in a receive window
+    WindowsClosed(Packet),                                      // Receive windows of a confirmed uplink close, its node retransmits it unless acknowledged
+    Retransmission(Packet),                                     // Node transmits an unacknowledged confirmed uplink again
+    Beacon,                                                     // Every gateway starts transmitting the Class B beacon
+    PingRequest { node_id: u32 },                               // Application asks for a downlink in a ping slot of a Class B node
+    ClassCRequest { node_id: u32 },                             // Application asks for a downlink to a Class C node
+    Multicast { index: u32 },                                   // Network server sends a fragment of the multicast session through every gateway of the group
+    Fragment { gateway: usize, index: u32, start: DateTime<Local> }, // Gateway ends the transmission of a multicast fragment started at `start`
+}
+
+/// Event scheduled at a simulated instant
+#[derive(Debug)]
+pub struct Event {
+    pub time: DateTime<Local>,  // Simulated time of the event
+    pub seq: u64,               // Insertion order, breaks ties between simultaneous events
+    pub kind: EventKind,        // What happens at this instant
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        self.time == other.time && self.seq == other.seq
+    }
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Event {
+    // Reversed so that the BinaryHeap pops the earliest event first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.time.cmp(&self.time).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// Event queue ordered by simulated time
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    heap: BinaryHeap<Event>,    // Pending events, earliest on top
+    next_seq: u64,              // Sequence number of the next scheduled event
+}
+
+impl EventQueue {
+    /// Create an empty event queue
+    pub fn new() -> Self {
+        EventQueue::default()
+    }
+
+    /// Schedule an event at the given simulated time
+    pub fn schedule(&mut self, time: DateTime<Local>, kind: EventKind) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.heap.push(Event { time, seq, kind });
+    }
+
+    /// Pop the earliest event
+    pub fn pop(&mut self) -> Option<Event> {
+        self.heap.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    /// Packet IDs of the timeouts in the order the queue yields them
+    fn popped(mut events: EventQueue) -> Vec<u32> {
+        std::iter::from_fn(|| events.pop())
+            .map(|event| match event.kind {
+                EventKind::Timeout { packet_id, .. } => packet_id,
+                kind => panic!("unexpected event {:?}", kind),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn events_pop_in_simulated_time_order() {
+        let start = Local::now();
+        let mut events = EventQueue::new();
+        for (packet_id, offset_ms) in [(1, 30), (2, 10), (3, 20), (4, 0)] {
+            events.schedule(start + Duration::milliseconds(offset_ms), EventKind::Timeout { gateway: 0, packet_id });
+        }
+        assert_eq!(popped(events), vec![4, 2, 3, 1]);
+    }
+
+    #[test]
+    fn simultaneous_events_pop_in_scheduling_order() {
+        let start = Local::now();
+        let mut events = EventQueue::new();
+        events.schedule(start + Duration::milliseconds(5), EventKind::Timeout { gateway: 0, packet_id: 1 });
+        for packet_id in 2..6 {
+            events.schedule(start, EventKind::Timeout { gateway: 0, packet_id });
+        }
+        events.schedule(start + Duration::milliseconds(5), EventKind::Timeout { gateway: 0, packet_id: 6 });
+        assert_eq!(popped(events), vec![2, 3, 4, 5, 1, 6]);
+    }
+}
\ No newline at end of file
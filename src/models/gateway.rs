@@ -15,17 +15,25 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use crate::models::packet::Packet;
-use crate::data_access::generate_packets::*;
-use crate::data_access::get_packets::*;
-use crate::data_access::setup_logger::*;
+use crate::models::admission::{Admission, AdmissionControl};
+use crate::models::packet::{Packet, PacketStream};
+use crate::models::retransmission::{Retransmission, RetransmissionConfig};
+use crate::models::channel::Channel;
+use crate::models::clock::Clock;
+use crate::models::interference::CaptureModel;
+use crate::models::network::{Coverage, GatewaySite, NetworkServer};
+use crate::models::node::Position;
+use crate::models::statistics::Statistics;
+use crate::models::strategy::{strategy_from_name, AllocationStrategy, STRATEGY_NAMES};
+use crate::models::trace::{Trace, TraceEvent};
+use crate::data_access::scenario::ScenarioConfig;
 
 use log::{info, error, warn};
-use chrono::{Local, Duration};
-use rand::Rng;
+use chrono::{DateTime, Local, Duration};
+use serde::{Serialize, Deserialize};
 use std::collections::VecDeque;
+use std::iter::Peekable;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
 
 /// Status of packet distribution
 #[derive(Debug)]
@@ -34,271 +42,196 @@ pub enum DistributeStatus {
     AllChannelsFull,         // All channels are full
     EmptyQueue,              // Pending queue is empty
     Timeout,                 // Packet timeout
+    Unsupported,             // No channel is tuned to the frequency and bandwidth of the packet, which is rejected
+}
+
+/// Decision on a packet taken out of the pending queue after timing out
+#[derive(Debug)]
+pub enum Resend {
+    Retry(Packet, Duration), // Re-enter the pending queue after the backoff
+    Exhausted,               // Dropped after the maximum number of retries
+}
+
+/// How the simulation advances time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SimulationMode {
+    EventDriven,             // Discrete events on a virtual clock, the default
+    RealTime,                // One thread per channel sleeping for the processing time
 }
 
 #[derive(Debug, Clone)]
 pub struct Gateway {
     pub id: u32,                                            // Gateway ID
+    pub position: Position,                                 // Where the gateway is deployed
     pub pending_queue: Arc<Mutex<VecDeque<Packet>>>,        // Pending queue for packets waiting to be processed
-    pub channel_queues: Arc<Mutex<[VecDeque<Packet>; 8]>>,  // Channel queues for packets being processed, 8 channels in total
+    pub channels: Arc<Mutex<Vec<Channel>>>,                 // Channels with their queues of packets being processed
     pub pending_queue_capacity: usize,                      // Capacity of the pending queue
-    pub channel_queue_capacity: usize,                      // Capacity of each channel queue
     pub should_exit: Arc<std::sync::atomic::AtomicBool>,    // Flag to indicate whether the gateway should exit
     pub time_threshold: Duration,                           // Time threshold for packet timeout
+    pub clock: Clock,                                       // Clock used for arrival and timeout checks
+    pub strategy: Arc<dyn AllocationStrategy>,              // Policy allocating pending packets to channels
+    pub capture: CaptureModel,                              // Capture effect of concurrent transmissions
+    pub trace: Trace,                                       // Lifecycle events of every packet
+    pub stats: Statistics,                                  // Measurements of the run
+    pub server: NetworkServer,                              // Network server the delivered packets are forwarded to
+    pub admission: Arc<Mutex<AdmissionControl>>,            // Overflow policy of the pending queue
+    pub retransmission: Arc<Mutex<Retransmission>>,         // Retry limit and backoff of timed-out packets
 }
 
 impl Gateway {
-    /// Create a new gateway
-    pub fn new(id: u32) -> Self {
-        Gateway {
-            id,
+    /// Create the gateway of a validated scenario deployed at `site`, its random streams derive from `seed`
+    pub fn from_config(scenario: &ScenarioConfig, site: &GatewaySite, seed: u64, server: NetworkServer) -> Result<Self, String> {
+        let config = &scenario.gateway;
+        let channels = scenario.gateway_channels();
+        // The nodes retransmit their confirmed uplinks, the gateway drops the packets timing out
+        let retransmission = if scenario.network.confirmed.enabled {
+            RetransmissionConfig { max_retries: 0, ..config.retransmission.clone() }
+        } else {
+            config.retransmission.clone()
+        };
+        let strategy = strategy_from_name(&config.strategy, seed)
+            .ok_or_else(|| format!("gateway.strategy must be one of {:?}, got {}", STRATEGY_NAMES, config.strategy))?;
+        Ok(Gateway {
+            id: site.id,
+            position: site.position,
             pending_queue: Arc::new(Mutex::new(VecDeque::new())),
-            channel_queues: Arc::new(Mutex::new([
-                                VecDeque::new(),
-                                VecDeque::new(),
-                                VecDeque::new(),
-                                VecDeque::new(),
-                                VecDeque::new(),
-                                VecDeque::new(),
-                                VecDeque::new(),
-                                VecDeque::new(),
-                            ])),
-            pending_queue_capacity: 1000,
-            channel_queue_capacity: 10,
+            channels: Arc::new(Mutex::new(channels.iter().map(Channel::new).collect())),
+            pending_queue_capacity: config.pending_queue_capacity,
             should_exit: Arc::new(std::sync::atomic::AtomicBool::new(false)),
-            time_threshold: Duration::seconds(10),
-        }
+            time_threshold: Duration::milliseconds(config.time_threshold_ms),
+            clock: Clock::Wall,
+            strategy,
+            capture: CaptureModel {
+                threshold_db: config.capture.threshold_db,
+                lock_symbols: config.capture.lock_symbols,
+                rejection_matrix: config.capture.rejection_matrix.resolve(),
+            },
+            stats: Statistics::new(channels.len()),
+            admission: Arc::new(Mutex::new(AdmissionControl::new(config.overflow.clone(), config.pending_queue_capacity, seed))),
+            retransmission: Arc::new(Mutex::new(Retransmission::new(&retransmission, seed))),
+            trace: Trace::disabled(),
+            server,
+        })
     }
 
     /// Check if the pending queue is empty
     pub fn is_pending_queue_empty(&self) -> bool {
         let queue = self.pending_queue.lock().unwrap();
-        queue.len() <= 0
+        queue.is_empty()
     }
 
-    // #[warn(dead_code)]
-    // /// Check if the pending queue is full
-    // pub fn is_pending_queue_full(&self) -> bool {
-    //     let queue = self.pending_queue.lock().unwrap();
-    //     queue.len() >= self.pending_queue_capacity
-    // }
-
     /// Check if a specific channel queue is empty
     pub fn is_channel_queue_empty(&self, i: usize) -> bool {
-        let queue = self.channel_queues.lock().unwrap();
-        queue[i].len() <= 0
-    }
-
-    /// Check if a specific channel queue is full
-    pub fn is_channel_queue_full(&self, i: usize) -> bool {
-        let queue = self.channel_queues.lock().unwrap();
-        queue[i].len() >= self.channel_queue_capacity
+        let channels = self.channels.lock().unwrap();
+        channels[i].queue.is_empty()
     }
 
     /// Check if the packet has timed out
     pub fn is_timeout(&self, packet: &Packet) -> bool {
-        Local::now().signed_duration_since(packet.arrival_time) > self.time_threshold
+        self.clock.now().signed_duration_since(packet.arrival_time) > self.time_threshold
     }
 
-    /// Resend a packet by removing it from the current queue, updating its arrival time, and re-adding it to the pending queue
-    pub fn resend_packet(&self) {
-        let mut pending_queue = self.pending_queue.lock().unwrap();
-        let mut packet = pending_queue.pop_front().unwrap();
-        warn!("\u{1F62D}: Packet {} is timeout, resent", packet.packet_id);
-        // if self.is_pending_queue_full() {
-        //     panic!("Pending queue is full, cannot resend packet");
-        // }
-        packet.arrival_time = Local::now();
-        pending_queue.push_back(packet);
+    /// Admit an arriving packet into the pending queue under the overflow policy, returns whether it was queued
+    pub fn admit_pending(&self, pending_queue: &mut VecDeque<Packet>, packet: Packet) -> bool {
+        let now = self.clock.now();
+        let admission = self.admission.lock().unwrap().decide(pending_queue, now, self.time_threshold);
+        match admission {
+            Admission::Admit => {}
+            Admission::Evict(index, reason) => {
+                let evicted = pending_queue.remove(index).unwrap();
+                warn!("\u{1F5D1}: Packet {} is dropped from the pending queue ({})", evicted.packet_id, reason);
+                self.drop_packet(evicted.packet_id, reason);
+            }
+            Admission::Reject(reason) => {
+                warn!("\u{1F5D1}: Packet {} is not admitted to the pending queue ({})", packet.packet_id, reason);
+                self.drop_packet(packet.packet_id, reason);
+                return false;
+            }
+        }
+
+        self.enqueue_pending(pending_queue, packet);
+        self.stats.pending_depth(pending_queue.len());
+        true
     }
 
-    // /// Distribute one packet to the channel queues in a round-robin manner
-    // pub fn distribute_one_packet(&self) -> DistributeStatus {
-    //     {
-    //         if self.is_pending_queue_empty() {
-    //             return DistributeStatus::EmptyQueue;
-    //         }
-    //     }
-    
-    //     {
-    //         let mut pending_queue = self.pending_queue.lock().unwrap();
-    
-    //         let packet = match pending_queue.front() {
-    //             Some(p) => p,
-    //             None => {
-    //                 error!("Unexpected empty pending queue");
-    //                 return DistributeStatus::EmptyQueue;
-    //             }
-    //         };
-    
-    //         if self.is_timeout(packet) {
-    //             return DistributeStatus::Timeout;
-    //         }
-    
-    //         for i in 0..8 {
-    //             if !self.is_channel_queue_full(i) {
-    //                 info!("Packet {} is allocated to channel {}", packet.packet_id, i);
-    //                 let packet = pending_queue.pop_front().unwrap();
-    //                 let mut channel_queues = self.channel_queues.lock().unwrap();
-    //                 channel_queues[i].push_back(packet);
-    //                 return DistributeStatus::Success(i);
-    //             }
-    //         }
-    //     }
-    
-    //     info!("\u{1F613}: All channels are full");
-    //     DistributeStatus::AllChannelsFull
-    // }
-
-    // /// Distribute one packet to the channel queues in a random manner
-    // pub fn distribute_one_packet(&self) -> DistributeStatus {
-    //     {
-    //         if self.is_pending_queue_empty() {
-    //             return DistributeStatus::EmptyQueue;
-    //         }
-    //     }
-    
-    //     {
-    //         let mut pending_queue = self.pending_queue.lock().unwrap();
-    
-    //         let packet = match pending_queue.front() {
-    //             Some(p) => p,
-    //             None => {
-    //                 error!("Unexpected empty pending queue");
-    //                 return DistributeStatus::EmptyQueue;
-    //             }
-    //         };
-    
-    //         if self.is_timeout(packet) {
-    //             return DistributeStatus::Timeout;
-    //         }
-    
-    //         let mut rng = rand::thread_rng();
-    //         let mut allocated = false;
-    //         let mut i = 0;
-    //         while i < 8 {
-    //             let j = rng.gen_range(0..8);
-    //             if !self.is_channel_queue_full(j) {
-    //                 info!("Packet {} is allocated to channel {}", packet.packet_id, j);
-    //                 let packet = pending_queue.pop_front().unwrap();
-    //                 let mut channel_queues = self.channel_queues.lock().unwrap();
-    //                 channel_queues[j].push_back(packet);
-    //                 allocated = true;
-    //                 break;
-    //             }
-    //             i += 1;
-    //         }
-
-    //         if allocated {
-    //             return DistributeStatus::Success(i);
-    //         }
-    //     }
-    
-    //     info!("\u{1F613}: All channels are full");
-    //     DistributeStatus::AllChannelsFull
-    // }
-
-    // /// Distribute one packet to the channel queues based on the load
-    // pub fn distribute_one_packet(&self) -> DistributeStatus {
-    //     {
-    //         if self.is_pending_queue_empty() {
-    //             return DistributeStatus::EmptyQueue;
-    //         }
-    //     }
-    
-    //     {
-    //         let mut pending_queue = self.pending_queue.lock().unwrap();
-    
-    //         let packet = match pending_queue.front() {
-    //             Some(p) => p,
-    //             None => {
-    //                 error!("Unexpected empty pending queue");
-    //                 return DistributeStatus::EmptyQueue;
-    //             }
-    //         };
-    
-    //         if self.is_timeout(packet) {
-    //             return DistributeStatus::Timeout;
-    //         }
-
-    //         if !(0..8).any(|i| !self.is_channel_queue_full(i)) {
-    //             info!("\u{1F613}: All channels are full");
-    //             return DistributeStatus::AllChannelsFull;
-    //         }
-
-    //         let channel_index = (0..8)
-    //             .filter(|i| !self.is_channel_queue_full(*i))
-    //             .min_by_key(|i| {
-    //                 let channel_queues = self.channel_queues.lock().unwrap();
-    //                 channel_queues[*i].len()
-    //             })
-    //             .unwrap();
-
-    //         if !self.is_channel_queue_full(channel_index) {
-    //             info!("Packet {} is allocated to channel {}", packet.packet_id, channel_index);
-    //             let packet = pending_queue.pop_front().unwrap();
-    //             let mut channel_queues = self.channel_queues.lock().unwrap();
-    //             channel_queues[channel_index].push_back(packet);
-    //             return DistributeStatus::Success(channel_index);
-    //         }
-    //     }
-    
-    //     info!("\u{1F613}: All channels are full");
-    //     DistributeStatus::AllChannelsFull
-    // }
-
-    // /// Distribute one packet to the channel queues based on the time load
-    // pub fn distribute_one_packet(&self) -> DistributeStatus {
-    //     {
-    //         if self.is_pending_queue_empty() {
-    //             return DistributeStatus::EmptyQueue;
-    //         }
-    //     }
-    
-    //     {
-    //         let mut pending_queue = self.pending_queue.lock().unwrap();
-    
-    //         let packet = match pending_queue.front() {
-    //             Some(p) => p,
-    //             None => {
-    //                 error!("Unexpected empty pending queue");
-    //                 return DistributeStatus::EmptyQueue;
-    //             }
-    //         };
-    
-    //         if self.is_timeout(packet) {
-    //             return DistributeStatus::Timeout;
-    //         }
-
-    //         // Create a tuple containing the channel index and the total time of the channel queue
-    //         let mut channel_time: Vec<(usize, Duration)> = (0..8)
-    //             .map(|i| {
-    //                 let channel_queues = self.channel_queues.lock().unwrap();
-    //                 let total_time = channel_queues[i].iter().map(|p| p.processing_time).sum();
-    //                 (i, total_time)
-    //             })
-    //             .collect();
-
-    //         // Sort by total_time in ascending order
-    //         channel_time.sort_by_key(|(_, total_time)| *total_time);
-
-    //         // Iterate over the channel_time's usize
-    //         for (i, _) in channel_time {
-    //             if !self.is_channel_queue_full(i) {
-    //                 info!("Packet {} is allocated to channel {}", packet.packet_id, i);
-    //                 let packet = pending_queue.pop_front().unwrap();
-    //                 let mut channel_queues = self.channel_queues.lock().unwrap();
-    //                 channel_queues[i].push_back(packet);
-    //                 return DistributeStatus::Success(i);
-    //             }
-    //         }
-    //     }
-    
-    //     info!("\u{1F613}: All channels are full");
-    //     DistributeStatus::AllChannelsFull
-    // }
+    /// Record that the gateway dropped a packet for the given reason
+    fn drop_packet(&self, packet_id: u32, reason: &str) {
+        self.trace.record(self.clock.now(), packet_id, TraceEvent::Outcome, None, Some(reason));
+        self.stats.dropped(packet_id, reason);
+    }
+
+    /// Insert a packet into the pending queue at the position chosen by the allocation strategy
+    pub fn enqueue_pending(&self, pending_queue: &mut VecDeque<Packet>, packet: Packet) {
+        let index = self.strategy.pending_position(pending_queue, &packet);
+        pending_queue.insert(index, packet);
+    }
+
+    /// Take the timed-out packet at the head of the pending queue out and decide on its retransmission
+    pub fn resend_packet(&self) -> Resend {
+        let packet = self.pending_queue.lock().unwrap().pop_front().unwrap();
+        self.retransmit(packet)
+    }
 
-    /// Distribute one packet to the channel queues based on the time load with pending queue optimization
+    /// Take a specific pending packet out if it has waited at least the time threshold, `None` if it has not
+    pub fn resend_expired_packet(&self, packet_id: u32) -> Option<Resend> {
+        let now = self.clock.now();
+        let packet = {
+            let mut pending_queue = self.pending_queue.lock().unwrap();
+            let index = pending_queue.iter().position(|p| {
+                p.packet_id == packet_id && now.signed_duration_since(p.arrival_time) >= self.time_threshold
+            })?;
+            pending_queue.remove(index).unwrap()
+        };
+        Some(self.retransmit(packet))
+    }
+
+    /// Count a retry of a timed-out packet and draw its backoff, or drop it once it has used all its retries
+    fn retransmit(&self, mut packet: Packet) -> Resend {
+        let now = self.clock.now();
+        self.stats.timeout();
+
+        let delay = {
+            let mut retransmission = self.retransmission.lock().unwrap();
+            if packet.retries >= retransmission.max_retries {
+                None
+            } else {
+                packet.retries += 1;
+                Some(retransmission.delay(packet.retries))
+            }
+        };
+
+        match delay {
+            Some(delay) => {
+                warn!("\u{1F62D}: Packet {} is timeout, resent (retry {}, backoff {} ms)",
+                    packet.packet_id, packet.retries, delay.num_milliseconds());
+                self.trace.record(now, packet.packet_id, TraceEvent::Timeout, None, Some("resent"));
+                self.stats.retransmission();
+                Resend::Retry(packet, delay)
+            }
+            None => {
+                warn!("\u{1F480}: Packet {} is dropped after {} retries", packet.packet_id, packet.retries);
+                self.trace.record(now, packet.packet_id, TraceEvent::Timeout, None, Some("retries-exhausted"));
+                self.drop_packet(packet.packet_id, "retries-exhausted");
+                Resend::Exhausted
+            }
+        }
+    }
+
+    /// Put packets whose backoff has elapsed back into the pending queue
+    pub fn release_retries(&self, retries: &mut Vec<(DateTime<Local>, Packet)>) {
+        let now = self.clock.now();
+        let (due, waiting): (Vec<_>, Vec<_>) = retries.drain(..).partition(|(time, _)| *time <= now);
+        *retries = waiting;
+
+        let mut pending_queue = self.pending_queue.lock().unwrap();
+        for (_, mut packet) in due {
+            packet.arrival_time = now;
+            self.admit_pending(&mut pending_queue, packet);
+        }
+    }
+
+    /// Distribute one packet to the channel queues using the allocation strategy
     pub fn distribute_one_packet(&self) -> DistributeStatus {
         {
             if self.is_pending_queue_empty() {
@@ -323,83 +256,68 @@ impl Gateway {
         }
 
         {
-            // For each packet in the pending_queue, sort them by processing_time, 
-            // with shorter processing_time being processed first
             let mut pending_queue = self.pending_queue.lock().unwrap();
-            pending_queue.make_contiguous().sort_by_key(|p| p.processing_time);
-
-            // Create a tuple containing the channel index and the total time of the channel queue
-            let mut channel_time: Vec<(usize, Duration)> = (0..8)
-                .map(|i| {
-                    let channel_queues = self.channel_queues.lock().unwrap();
-                    let total_time = channel_queues[i].iter().map(|p| p.processing_time).sum();
-                    (i, total_time)
-                })
-                .collect();
-
-            // Sort by total_time in ascending order
-            channel_time.sort_by_key(|(_, total_time)| *total_time);
-
-            // Iterate over the channel_time's usize
-            for (i, _) in channel_time {
-                if !self.is_channel_queue_full(i) {
-                    let packet = pending_queue.pop_front().unwrap();
-                    info!("Packet {} is allocated to channel {}", packet.packet_id, i);
-                    let mut channel_queues = self.channel_queues.lock().unwrap();
-                    channel_queues[i].push_back(packet);
-                    return DistributeStatus::Success(i);
-                }
+            let mut channels = self.channels.lock().unwrap();
+            let packet = pending_queue.front().unwrap();
+            if !channels.iter().any(|c| c.tuned_to(packet)) {
+                let packet = pending_queue.pop_front().unwrap();
+                warn!("Packet {} is dropped, no channel is tuned to {:.1} MHz with a bandwidth of {} Hz",
+                    packet.packet_id, f64::from(packet.phy.frequency) / 1e6, packet.phy.bandwidth);
+                self.drop_packet(packet.packet_id, "unsupported");
+                return DistributeStatus::Unsupported;
+            }
+            if let Some(i) = self.strategy.select_channel(packet, &channels[..]) {
+                let packet = pending_queue.pop_front().unwrap();
+                info!("Packet {} is allocated to channel {} ({:.1} MHz, {} kHz)",
+                    packet.packet_id, i, f64::from(channels[i].frequency) / 1e6, channels[i].bandwidth / 1000);
+                self.trace.record(self.clock.now(), packet.packet_id, TraceEvent::AllocationAttempt, Some(i), None);
+                channels[i].queue.push_back(packet);
+                return DistributeStatus::Success(i);
             }
+            self.trace.record(self.clock.now(), packet.packet_id, TraceEvent::AllocationAttempt, None, Some("all channels full"));
         }
     
         info!("\u{1F613}: All channels are full");
         DistributeStatus::AllChannelsFull
     }
 
-    /// Initialize the gateway
-    pub fn init(&self) {
-        setup_logger().unwrap();
-
-        let args: Vec<String> = std::env::args().collect();
-        let packet_queue = if args.contains(&String::from("--random")) {
-            match generate_random_packet() {
-                Ok(queue) => {
-                    info!("Generated random packets: {}", queue.len());
-                    queue
-                },
-                Err(e) => {
-                    error!("Failed to generate packets: {}", e);
-                    panic!("Failed to generate packets: {}", e);
-                }
-            }
-        } else {
-            match load_data() {
-                Ok(queue) => {
-                    info!("Loaded packets from file: {}", queue.len());
-                    queue
-                },
-                Err(e) => {
-                    error!("Failed to load packets: {}", e);
-                    panic!("Failed to load packets: {}", e);
-                }
-            }
-        };
+    /// Move the packets due by now and heard by the gateway into the pending queue,
+    /// the offsets of `arrivals` from `origin` are replayed from `start`
+    pub fn release_arrivals(&self, arrivals: &mut Peekable<PacketStream>, origin: DateTime<Local>, start: DateTime<Local>,
+        coverage: &mut Coverage) {
+        let now = self.clock.now();
+        let mut pending_queue = self.pending_queue.lock().unwrap();
 
-        {
-            let mut pending_queue = self.pending_queue.lock().unwrap();
-            pending_queue.clear();
-            pending_queue.extend(packet_queue);
+        while let Some(mut packet) = arrivals.next_if(|p| start + p.arrival_time.signed_duration_since(origin) <= now) {
+            let Some(link) = coverage.link(&packet, self.id, &self.position) else {
+                warn!("Packet {} of node {} is below the sensitivity of the gateway", packet.packet_id, packet.node_id);
+                self.server.uplink(&packet, false);
+                continue;
+            };
+
+            let arrival_time = start + packet.arrival_time.signed_duration_since(origin);
+            packet.arrival_time = arrival_time;
+            packet.rssi = link.rssi;
+            packet.snr = link.snr;
+            self.trace.record(arrival_time, packet.packet_id, TraceEvent::Arrival, None, None);
+            self.stats.arrival(packet.packet_id, packet.node_id, arrival_time);
+            self.stats.link(link.rssi, link.snr);
+            self.server.uplink(&packet, true);
+            self.admit_pending(&mut pending_queue, packet);
         }
     }
 
-    pub fn simulation(&self) {
-        self.init();
-        let gateway = Arc::new(self.clone());
-
-        let start_time = Instant::now();
+    /// Run in real time with one thread per channel, the offsets of `arrivals` from `origin` are replayed from `start`
+    ///
+    /// Returns once every packet is processed, at the instant the run ends
+    pub fn run_real_time(self, mut arrivals: Peekable<PacketStream>, origin: DateTime<Local>, start: DateTime<Local>,
+        mut coverage: Coverage) -> DateTime<Local> {
+        let mut retries = Vec::new();
+        let gateway = Arc::new(self);
 
         let mut threads = vec![];
-        for i in 0..8 {
+        let channel_count = gateway.channels.lock().unwrap().len();
+        for i in 0..channel_count {
             let gateway = Arc::clone(&gateway);
             let handle = std::thread::spawn(move || {
                 while !gateway.should_exit.load(std::sync::atomic::Ordering::Relaxed) {
@@ -412,12 +330,14 @@ impl Gateway {
                 
                     // 2. Process the packet
                     let packet = {
-                        let mut channel_queues = gateway.channel_queues.lock().unwrap();
-                        let packet = channel_queues[i].pop_front().unwrap();
-                        packet
+                        let mut channels = gateway.channels.lock().unwrap();
+                        channels[i].queue.pop_front().unwrap()
                     };
                 
                     info!("\u{1F600}: Packet {} is processing in channel {}", packet.packet_id, i);
+                    let service_start = Local::now();
+                    gateway.trace.record(service_start, packet.packet_id, TraceEvent::ServiceStart, Some(i), None);
+                    gateway.stats.service_start(packet.packet_id, service_start);
                     
                     // 3. Add timeout detection
                     let start = std::time::Instant::now();
@@ -430,6 +350,11 @@ impl Gateway {
                     let elapsed = start.elapsed();
                     info!("\u{1F60A}: Packet {} processed in channel {} (took {:?})", 
                         packet.packet_id, i, elapsed);
+                    let service_end = Local::now();
+                    gateway.trace.record(service_end, packet.packet_id, TraceEvent::Completion, Some(i), None);
+                    gateway.trace.record(service_end, packet.packet_id, TraceEvent::Outcome, Some(i), Some("delivered"));
+                    gateway.stats.completion(packet.packet_id, i, service_start, service_end);
+                    gateway.server.deliver(&packet);
                     
                     if elapsed > processing_time_std * 2 {
                         warn!("Packet {} processing took longer than expected in channel {}", 
@@ -441,6 +366,9 @@ impl Gateway {
         }
 
         loop {
+            gateway.release_arrivals(&mut arrivals, origin, start, &mut coverage);
+            gateway.release_retries(&mut retries);
+
             match gateway.distribute_one_packet() {
                 DistributeStatus::Success(_) => {
                     std::thread::sleep(std::time::Duration::from_micros(100));
@@ -448,11 +376,19 @@ impl Gateway {
                 DistributeStatus::AllChannelsFull => {
                     std::thread::sleep(std::time::Duration::from_millis(1));
                 }
+                DistributeStatus::Unsupported => {}
                 DistributeStatus::Timeout => {
-                    gateway.resend_packet();
+                    if let Resend::Retry(packet, delay) = gateway.resend_packet() {
+                        retries.push((Local::now() + delay, packet));
+                    }
                     std::thread::sleep(std::time::Duration::from_micros(500));
                 }
                 DistributeStatus::EmptyQueue => {
+                    // Wait for the packets still to arrive or be retried and let the channels drain their queues before stopping them
+                    if arrivals.peek().is_some() || !retries.is_empty() || (0..channel_count).any(|i| !gateway.is_channel_queue_empty(i)) {
+                        std::thread::sleep(std::time::Duration::from_millis(1));
+                        continue;
+                    }
                     gateway.should_exit.store(true, std::sync::atomic::Ordering::Relaxed);
                     break;
                 }
@@ -462,10 +398,58 @@ impl Gateway {
         for handle in threads {
             handle.join().unwrap();
         }
-        
-        info!("All packets are processed, waiting for channels to finish");
 
-        let elapsed = start_time.elapsed();
-        println!("Total processing time: {:?}", elapsed);
+        Local::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::phy::PhyParams;
+    use crate::models::retransmission::Backoff;
+
+    #[test]
+    fn packet_without_a_tuned_channel_is_rejected() {
+        let scenario = ScenarioConfig::default();
+        let server = NetworkServer::from_config(&scenario, 1);
+        let gateway = Gateway::from_config(&scenario, &GatewaySite { id: 1, position: Position::default() }, 1, server).unwrap();
+        for (packet_id, frequency) in [(1, 868_100_000), (2, 915_200_000)] {
+            let phy = PhyParams { frequency, ..PhyParams::default() };
+            gateway.pending_queue.lock().unwrap().push_back(Packet::from_phy(packet_id, phy, gateway.clock.now()));
+        }
+
+        assert!(matches!(gateway.distribute_one_packet(), DistributeStatus::Success(_)));
+        assert!(matches!(gateway.distribute_one_packet(), DistributeStatus::Unsupported));
+        assert!(gateway.is_pending_queue_empty());
+    }
+
+    #[test]
+    fn unknown_strategy_is_an_error() {
+        let mut scenario = ScenarioConfig::default();
+        scenario.gateway.strategy = String::from("fastest");
+        let server = NetworkServer::from_config(&scenario, 1);
+        let error = Gateway::from_config(&scenario, &GatewaySite { id: 1, position: Position::default() }, 1, server).unwrap_err();
+        assert!(error.starts_with("gateway.strategy must be one of"), "{}", error);
+    }
+
+    #[test]
+    fn packet_is_dropped_once_it_has_used_its_retries() {
+        let mut scenario = ScenarioConfig::default();
+        scenario.gateway.retransmission = RetransmissionConfig { max_retries: 2, backoff: Backoff::Fixed { delay_ms: 100.0 } };
+        let server = NetworkServer::from_config(&scenario, 1);
+        let gateway = Gateway::from_config(&scenario, &GatewaySite { id: 1, position: Position::default() }, 1, server).unwrap();
+        let mut packet = Packet::from_phy(1, PhyParams::default(), gateway.clock.now());
+
+        for retry in 1..=2 {
+            match gateway.retransmit(packet) {
+                Resend::Retry(resent, delay) => {
+                    assert_eq!((resent.retries, delay), (retry, Duration::milliseconds(100)));
+                    packet = resent;
+                }
+                Resend::Exhausted => panic!("packet dropped before retry {}", retry),
+            }
+        }
+        assert!(matches!(gateway.retransmit(packet), Resend::Exhausted));
     }
 }
\ No newline at end of file
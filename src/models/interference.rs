@@ -0,0 +1,317 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::packet::Packet;
+
+use chrono::{DateTime, Local, Duration};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Outcome of receiving a transmission at the gateway
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReceptionOutcome {
+    Received,                // No harmful overlap
+    Captured,                // Overlapped but strong enough to be demodulated
+    Collided,                // Lost to collision
+    Blocked,                 // Lost while the gateway was transmitting a downlink
+}
+
+impl ReceptionOutcome {
+    /// Name of the outcome in logs and exported files
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReceptionOutcome::Received => "received",
+            ReceptionOutcome::Captured => "captured",
+            ReceptionOutcome::Collided => "collided",
+            ReceptionOutcome::Blocked => "blocked",
+        }
+    }
+}
+
+/// Co-channel rejection between spreading factors, rows are the wanted SF7 to SF12 and columns the interfering SF7 to SF12
+///
+/// The diagonal is kept as published but unused, the same spreading factor needs the co-SF capture threshold
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RejectionMatrix(pub [[f64; 6]; 6]);
+
+/// Names of the built-in rejection matrices, `none` assumes perfectly orthogonal spreading factors
+pub const REJECTION_MATRIX_NAMES: [&str; 3] = ["none", "croce", "goursaud"];
+
+impl RejectionMatrix {
+    /// SIR thresholds in dB measured by Croce et al., "Impact of LoRa Imperfect Orthogonality", 2018
+    pub fn croce() -> Self {
+        RejectionMatrix([
+            [  1.0,  -8.0,  -9.0,  -9.0,  -9.0,  -9.0],
+            [-11.0,   1.0, -11.0, -12.0, -13.0, -13.0],
+            [-15.0, -13.0,   1.0, -13.0, -14.0, -15.0],
+            [-19.0, -18.0, -17.0,   1.0, -17.0, -18.0],
+            [-22.0, -22.0, -21.0, -20.0,   1.0, -20.0],
+            [-25.0, -25.0, -25.0, -24.0, -23.0,   1.0],
+        ])
+    }
+
+    /// SIR thresholds in dB given by Goursaud and Gorce, "Dedicated Networks for IoT: PHY/MAC State of the Art and Challenges", 2015
+    pub fn goursaud() -> Self {
+        RejectionMatrix([
+            [  6.0, -16.0, -18.0, -19.0, -19.0, -20.0],
+            [-24.0,   6.0, -20.0, -22.0, -22.0, -22.0],
+            [-27.0, -27.0,   6.0, -23.0, -25.0, -25.0],
+            [-30.0, -30.0, -30.0,   6.0, -26.0, -28.0],
+            [-33.0, -33.0, -33.0, -33.0,   6.0, -29.0],
+            [-36.0, -36.0, -36.0, -36.0, -36.0,   6.0],
+        ])
+    }
+
+    /// Build a matrix from its name, `Some(None)` for perfectly orthogonal spreading factors
+    pub fn from_name(name: &str) -> Option<Option<Self>> {
+        match name {
+            "none" => Some(None),
+            "croce" => Some(Some(RejectionMatrix::croce())),
+            "goursaud" => Some(Some(RejectionMatrix::goursaud())),
+            _ => None,
+        }
+    }
+
+    /// Minimum SIR in dB for the wanted spreading factor to survive the interfering one
+    pub fn threshold(&self, wanted: u8, interferer: u8) -> f64 {
+        let index = |sf: u8| usize::from(sf.clamp(7, 12) - 7);
+        self.0[index(wanted)][index(interferer)]
+    }
+}
+
+/// Parameters of the capture effect
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CaptureModel {
+    pub threshold_db: f64,         // Minimum SIR against the same SF, in place of the diagonal of the rejection matrix
+    pub lock_symbols: u16,         // Preamble symbols the receiver needs to lock onto a packet
+    pub rejection_matrix: Option<RejectionMatrix>, // Inter-SF rejection, `None` for perfectly orthogonal spreading factors
+}
+
+impl Default for CaptureModel {
+    fn default() -> Self {
+        CaptureModel {
+            threshold_db: 6.0,
+            lock_symbols: 5,
+            rejection_matrix: Some(RejectionMatrix::croce()),
+        }
+    }
+}
+
+impl CaptureModel {
+    /// Minimum SIR in dB for the wanted spreading factor against an interferer, `None` if they do not interfere
+    ///
+    /// The same spreading factor always needs `threshold_db`, the rejection matrix only gives the other ones
+    pub fn sir_threshold(&self, wanted: u8, interferer: u8) -> Option<f64> {
+        if wanted == interferer {
+            return Some(self.threshold_db);
+        }
+        self.rejection_matrix.map(|matrix| matrix.threshold(wanted, interferer))
+    }
+}
+
+/// Transmission on the air
+#[derive(Debug, Clone)]
+pub struct Transmission {
+    pub spreading_factor: u8,      // Spreading factor
+    pub rssi: f64,                 // Received power at the gateway in dBm
+    pub start: DateTime<Local>,    // Start of the transmission
+    pub end: DateTime<Local>,      // End of the transmission
+    pub critical: DateTime<Local>, // Instant from which the receiver locks onto the preamble
+}
+
+/// Key of a tracked transmission, its end then the order it started in
+type EndKey = (DateTime<Local>, u64);
+
+/// Tracks transmissions on the air and decides their outcome
+///
+/// Transmissions are indexed by frequency and end, so deciding an outcome only visits the transmissions
+/// still on the air on its frequency
+#[derive(Debug, Default)]
+pub struct Interference {
+    capture: CaptureModel,              // Capture effect parameters
+    transmissions: HashMap<u32, BTreeMap<EndKey, Transmission>>, // By frequency, those that may still overlap an unresolved one
+    unresolved: BTreeMap<(u32, u64), (u32, EndKey)>, // Frequency and key of the undecided transmissions, by packet ID and order
+    starts: BTreeSet<(DateTime<Local>, u64)>, // Start of the undecided transmissions
+    started: u64,                       // Transmissions registered so far
+}
+
+impl Interference {
+    /// Create a tracker with the given capture model
+    pub fn new(capture: CaptureModel) -> Self {
+        Interference {
+            capture,
+            ..Interference::default()
+        }
+    }
+
+    /// Register a packet starting its transmission at the given instant
+    pub fn start(&mut self, packet: &Packet, start: DateTime<Local>) {
+        let phy = &packet.phy;
+        let unlocked = i64::from(phy.preamble_length.saturating_sub(self.capture.lock_symbols));
+        let critical = start + Duration::microseconds((unlocked as f64 * phy.symbol_time() * 1e6).round() as i64);
+
+        let transmission = Transmission {
+            spreading_factor: phy.spreading_factor,
+            rssi: packet.rssi,
+            start,
+            end: start + phy.time_on_air(),
+            critical,
+        };
+        let order = self.started;
+        self.started += 1;
+        self.unresolved.insert((packet.packet_id, order), (phy.frequency, (transmission.end, order)));
+        self.starts.insert((start, order));
+        self.transmissions.entry(phy.frequency).or_default().insert((transmission.end, order), transmission);
+    }
+
+    /// Decide the outcome of a finished transmission
+    pub fn resolve(&mut self, packet_id: u32) -> ReceptionOutcome {
+        let Some((&id, &(frequency, key))) = self.unresolved.range((packet_id, 0)..=(packet_id, u64::MAX)).next() else {
+            return ReceptionOutcome::Received;
+        };
+        let on_air = &self.transmissions[&frequency];
+        let target = &on_air[&key];
+
+        // Interferers that are still on the air when the receiver locks onto the preamble
+        let mut harmful = 0;
+        let mut interference_mw = [0.0; 6];
+        let after_lock = (target.critical, u64::MAX);
+        for (_, t) in on_air.range(after_lock..).filter(|(other, t)| **other != key && t.start < target.end) {
+            if self.capture.sir_threshold(target.spreading_factor, t.spreading_factor).is_some() {
+                harmful += 1;
+                interference_mw[usize::from(t.spreading_factor.clamp(7, 12) - 7)] += 10f64.powf(t.rssi / 10.0);
+            }
+        }
+
+        // The SIR against the interferers of each spreading factor must reach the rejection threshold
+        let survives = (7..=12).all(|sf| {
+            let total_mw = interference_mw[usize::from(sf - 7)];
+            match self.capture.sir_threshold(target.spreading_factor, sf) {
+                Some(threshold) if total_mw > 0.0 => target.rssi - 10.0 * total_mw.log10() >= threshold,
+                _ => true,
+            }
+        });
+
+        let outcome = if harmful == 0 {
+            ReceptionOutcome::Received
+        } else if survives {
+            ReceptionOutcome::Captured
+        } else {
+            ReceptionOutcome::Collided
+        };
+
+        self.starts.remove(&(target.start, key.1));
+        self.unresolved.remove(&id);
+        self.prune();
+        outcome
+    }
+
+    /// Start of the earliest transmission whose outcome is not decided yet
+    pub fn earliest_unresolved(&self) -> Option<DateTime<Local>> {
+        self.starts.first().map(|&(start, _)| start)
+    }
+
+    /// Forget resolved transmissions that can no longer overlap an unresolved one
+    ///
+    /// An undecided transmission ends after the earliest undecided start, so every transmission ending by then is decided
+    fn prune(&mut self) {
+        let earliest = self.earliest_unresolved();
+        self.transmissions.retain(|_, on_air| {
+            while on_air.first_key_value().is_some_and(|(&(end, _), _)| earliest.is_none_or(|earliest| end <= earliest)) {
+                on_air.pop_first();
+            }
+            !on_air.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::phy::PhyParams;
+
+    fn packet(packet_id: u32, spreading_factor: u8, rssi: f64, start: DateTime<Local>) -> Packet {
+        let phy = PhyParams { spreading_factor, ..PhyParams::default() };
+        Packet { rssi, ..Packet::from_phy(packet_id, phy, start) }
+    }
+
+    /// Outcomes of two transmissions, the second starting `offset_ms` after the first
+    fn outcomes(capture: CaptureModel, first: (u8, f64), second: (u8, f64), offset_ms: i64) -> (ReceptionOutcome, ReceptionOutcome) {
+        let mut interference = Interference::new(capture);
+        let start = Local::now();
+        interference.start(&packet(1, first.0, first.1, start), start);
+        let later = start + Duration::milliseconds(offset_ms);
+        interference.start(&packet(2, second.0, second.1, later), later);
+        (interference.resolve(1), interference.resolve(2))
+    }
+
+    fn orthogonal() -> CaptureModel {
+        CaptureModel { rejection_matrix: None, ..CaptureModel::default() }
+    }
+
+    #[test]
+    fn same_sf_strongest_is_captured() {
+        let (strong, weak) = outcomes(orthogonal(), (7, -80.0), (7, -90.0), 10);
+        assert_eq!(strong, ReceptionOutcome::Captured);
+        assert_eq!(weak, ReceptionOutcome::Collided);
+    }
+
+    #[test]
+    fn same_sf_below_threshold_both_collide() {
+        assert_eq!(outcomes(orthogonal(), (7, -80.0), (7, -83.0), 10), (ReceptionOutcome::Collided, ReceptionOutcome::Collided));
+    }
+
+    #[test]
+    fn disjoint_transmissions_are_received() {
+        assert_eq!(outcomes(orthogonal(), (7, -80.0), (7, -80.0), 100), (ReceptionOutcome::Received, ReceptionOutcome::Received));
+    }
+
+    #[test]
+    fn same_sf_capture_follows_the_threshold_with_a_rejection_matrix() {
+        // 4 dB apart, enough for a 3 dB threshold but not for the default 6 dB
+        let lenient = CaptureModel { threshold_db: 3.0, ..CaptureModel::default() };
+        assert_eq!(outcomes(lenient, (7, -80.0), (7, -84.0), 10), (ReceptionOutcome::Captured, ReceptionOutcome::Collided));
+        assert_eq!(outcomes(CaptureModel::default(), (7, -80.0), (7, -84.0), 10), (ReceptionOutcome::Collided, ReceptionOutcome::Collided));
+    }
+
+    #[test]
+    fn orthogonal_spreading_factors_do_not_interfere() {
+        assert_eq!(outcomes(orthogonal(), (7, -80.0), (9, -60.0), 10), (ReceptionOutcome::Received, ReceptionOutcome::Received));
+    }
+
+    #[test]
+    fn cross_sf_is_captured_above_rejection_threshold() {
+        // Croce et al. give -9 dB for SF7 against SF9 and -15 dB for SF9 against SF7
+        let (sf7, sf9) = outcomes(CaptureModel::default(), (7, -100.0), (9, -95.0), 10);
+        assert_eq!(sf7, ReceptionOutcome::Captured);
+        assert_eq!(sf9, ReceptionOutcome::Captured);
+    }
+
+    #[test]
+    fn cross_sf_collides_below_rejection_threshold() {
+        let (sf7, sf9) = outcomes(CaptureModel::default(), (7, -100.0), (9, -85.0), 10);
+        assert_eq!(sf7, ReceptionOutcome::Collided);
+        assert_eq!(sf9, ReceptionOutcome::Captured);
+    }
+
+    #[test]
+    fn rejection_matrix_rows_are_wanted_sf() {
+        let croce = RejectionMatrix::croce();
+        assert_eq!(croce.threshold(7, 9), -9.0);
+        assert_eq!(croce.threshold(9, 7), -15.0);
+    }
+}
\ No newline at end of file
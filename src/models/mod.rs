@@ -16,4 +16,27 @@
  */
 
 pub mod packet;
-pub mod gateway;
\ No newline at end of file
+pub mod phy;
+pub mod gateway;
+pub mod channel;
+pub mod clock;
+pub mod event;
+pub mod engine;
+pub mod strategy;
+pub mod interference;
+pub mod trace;
+pub mod statistics;
+pub mod rng;
+pub mod arrival;
+pub mod admission;
+pub mod retransmission;
+pub mod node;
+pub mod propagation;
+pub mod network;
+pub mod region;
+pub mod duty_cycle;
+pub mod adr;
+pub mod downlink;
+pub mod confirmed;
+pub mod class_b;
+pub mod class_c;
\ No newline at end of file
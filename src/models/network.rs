@@ -0,0 +1,656 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::models::adr::{AdaptedUplinks, Adr};
+use crate::models::class_b::ClassB;
+use crate::models::class_c::ClassC;
+use crate::models::clock::Clock;
+use crate::models::confirmed::Retransmitter;
+use crate::models::downlink::{Downlink, DownlinkConfig, MissedDownlink, ReceiveWindow};
+use crate::models::duty_cycle::{DutyCycle, RegulatedUplinks};
+use crate::models::engine::EventEngine;
+use crate::models::gateway::{Gateway, SimulationMode};
+use crate::models::node::{Node, Position};
+use crate::models::packet::{Packet, PacketStream};
+use crate::models::phy::PhyParams;
+use crate::models::propagation::{Link, Propagation};
+use crate::models::region::Region;
+use crate::models::rng::{gateway_seed, stream_rng, RngStream};
+use crate::models::statistics::{ratio, ConfirmedReport, DownlinkReport, DutyCycleReport, NetworkReport, Report};
+use crate::models::trace::Trace;
+use crate::data_access::export_statistics::*;
+use crate::data_access::export_trace::*;
+use crate::data_access::generate_packets::*;
+use crate::data_access::get_packets::*;
+use crate::data_access::scenario::ScenarioConfig;
+use crate::data_access::setup_logger::*;
+
+use log::{info, error, warn};
+use chrono::{DateTime, Local};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Where a gateway of the network is deployed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GatewaySite {
+    pub id: u32,                    // Gateway ID
+    #[serde(default)]
+    pub position: Position,         // Position on the deployment plane
+}
+
+/// Decides which gateways hear the uplinks of the nodes and at which power
+#[derive(Debug)]
+pub struct Coverage {
+    propagation: Propagation,               // Path loss, shadowing, noise and sensitivity
+    nodes: HashMap<u32, Node>,              // Nodes by ID, with their position and transmission power
+    shadowing: HashMap<(u32, u32), f64>,    // Shadowing of each node and gateway pair drawn so far
+    rng: ChaCha8Rng,                        // Shadowing stream
+}
+
+impl Coverage {
+    /// Coverage of the given nodes, the shadowing of the run seeded with `seed`
+    pub fn new(propagation: Propagation, nodes: Vec<Node>, seed: u64) -> Self {
+        Coverage {
+            propagation,
+            nodes: nodes.into_iter().map(|node| (node.id, node)).collect(),
+            shadowing: HashMap::new(),
+            rng: stream_rng(seed, RngStream::Shadowing),
+        }
+    }
+
+    /// Link of a packet to the gateway `gateway_id` at `position`, `None` below the sensitivity of its spreading factor
+    ///
+    /// Packets of nodes with unknown positions are received at their stored RSSI
+    pub fn link(&mut self, packet: &Packet, gateway_id: u32, position: &Position) -> Option<Link> {
+        let rssi = match self.nodes.get(&packet.node_id) {
+            Some(node) => {
+                let tx_power_dbm = packet.tx_power_dbm.unwrap_or(node.radio.tx_power_dbm);
+                tx_power_dbm - self.loss_db(packet.node_id, gateway_id, position, packet.phy.frequency)
+            }
+            None => packet.rssi,
+        };
+        self.propagation.link(rssi, packet.phy.spreading_factor, packet.phy.bandwidth)
+    }
+
+    /// Link of a downlink from the gateway `gateway_id` at `position` to its node, `None` below the sensitivity of the node
+    ///
+    /// The link is reciprocal, with the same path loss and shadowing as the uplinks of the node
+    pub fn downlink(&mut self, downlink: &Downlink, gateway_id: u32, position: &Position) -> Option<Link> {
+        let rssi = if self.nodes.contains_key(&downlink.node_id) {
+            downlink.tx_power_dbm - self.loss_db(downlink.node_id, gateway_id, position, downlink.phy.frequency)
+        } else {
+            downlink.rssi
+        };
+        self.propagation.link(rssi, downlink.phy.spreading_factor, downlink.phy.bandwidth)
+    }
+
+    /// Path loss and shadowing between a known node and the gateway `gateway_id` at `position`
+    fn loss_db(&mut self, node_id: u32, gateway_id: u32, position: &Position, frequency: u32) -> f64 {
+        let node = &self.nodes[&node_id];
+        let shadowing = *self.shadowing
+            .entry((node_id, gateway_id))
+            .or_insert_with(|| self.propagation.shadowing_db(&mut self.rng));
+        self.propagation.path_loss.path_loss_db(node.position.distance(position), frequency) + shadowing
+    }
+}
+
+/// Network server receiving the packets delivered by every gateway, keeps one copy of each uplink
+/// and answers some of them with a downlink
+#[derive(Debug, Clone)]
+pub struct NetworkServer {
+    inner: Arc<Mutex<ServerState>>,
+    adr: Adr,                           // Adaptive data rate learning from the delivered packets
+    downlink: DownlinkConfig,           // Receive windows and downlinks of the nodes
+    region: Region,                     // Region giving the channels and data rates of the receive windows
+    confirmed: bool,                    // Whether the uplinks are confirmed, each acknowledged by a downlink
+    class_b: ClassB,                    // Beacons and ping slots of the Class B nodes
+    class_c: ClassC,                    // Unicast and multicast downlinks of the Class C nodes
+    gateway_duty_cycle: DutyCycle,      // Airtime of the gateways in the sub-bands of the region
+}
+
+#[derive(Debug)]
+struct ServerState {
+    uplinks: u64,                       // Uplinks transmitted by the nodes
+    unheard: u64,                       // Uplinks no gateway could hear
+    frames: HashMap<(u32, u32), u32>,   // Latest transmission delivered of each frame, by node ID and frame counter
+    duplicates: u64,                    // Further copies of uplinks already delivered
+    downlinks: DownlinkReport,          // Downlinks requested, sent, missed and received so far
+    acknowledged: HashSet<(u32, u32)>,  // Node ID and frame counter of the confirmed uplinks whose node heard an ACK
+    confirmed: ConfirmedReport,         // Confirmed frames their node is done with
+    delivered_confirmed: u64,           // Confirmed frames the network server received
+    delivered_attempts: u64,            // Transmissions of these frames
+    rng: ChaCha8Rng,                    // Downlink stream, draws the uplinks the application answers
+}
+
+impl NetworkServer {
+    /// Network server of a validated scenario that has not received anything yet, its random stream derives from `seed`
+    pub fn from_config(scenario: &ScenarioConfig, seed: u64) -> Self {
+        let downlink = scenario.network.downlink.clone();
+        let state = ServerState {
+            uplinks: 0,
+            unheard: 0,
+            frames: HashMap::new(),
+            duplicates: 0,
+            downlinks: DownlinkReport::default(),
+            acknowledged: HashSet::new(),
+            confirmed: ConfirmedReport::default(),
+            delivered_confirmed: 0,
+            delivered_attempts: 0,
+            rng: stream_rng(seed, RngStream::Downlink),
+        };
+        NetworkServer {
+            inner: Arc::new(Mutex::new(state)),
+            adr: Adr::new(scenario.network.adr.clone(), scenario.region, downlink.enabled),
+            region: scenario.region.unwrap_or(Region::Eu868),
+            confirmed: scenario.network.confirmed.enabled,
+            class_b: ClassB::new(scenario.network.class_b.clone(), scenario.region.unwrap_or(Region::Eu868), downlink.clone(), seed),
+            class_c: ClassC::new(scenario.network.class_c.clone(), scenario.region.unwrap_or(Region::Eu868), downlink.clone(), seed),
+            downlink,
+            gateway_duty_cycle: DutyCycle::new(scenario.duty_cycle_sub_bands()),
+        }
+    }
+
+    /// A node transmits an uplink, heard by at least one gateway or by none
+    ///
+    /// Only the first transmission of a frame counts, its retransmissions are reported with the confirmed uplinks
+    pub fn uplink(&self, packet: &Packet, heard: bool) {
+        if packet.attempt > 0 {
+            return;
+        }
+        let mut state = self.inner.lock().unwrap();
+        state.uplinks += 1;
+        if !heard {
+            state.unheard += 1;
+        }
+    }
+
+    /// A gateway forwards a delivered packet, returns whether it is the first copy of this transmission of its frame
+    ///
+    /// Copies of a frame already delivered count as duplicates, but a retransmission is answered again
+    pub fn deliver(&self, packet: &Packet) -> bool {
+        let mut state = self.inner.lock().unwrap();
+        let key = (packet.node_id, packet.frame_counter);
+        let first = match state.frames.get(&key) {
+            Some(&latest) => {
+                state.duplicates += 1;
+                latest < packet.attempt
+            }
+            None => true,
+        };
+        if first {
+            state.frames.insert(key, packet.attempt);
+        }
+        self.adr.observe(packet);
+        first
+    }
+
+    /// Whether the network server answers a delivered uplink, to send the node a MAC command or for the application
+    pub fn wants_downlink(&self, packet: &Packet) -> bool {
+        if !self.downlink.enabled {
+            return false;
+        }
+        let mut state = self.inner.lock().unwrap();
+        let wanted = self.confirmed || self.adr.has_command(packet.node_id) || state.rng.gen_bool(self.downlink.probability);
+        if wanted {
+            state.downlinks.requested += 1;
+        }
+        wanted
+    }
+
+    /// Downlink answering a delivered uplink, which ended at `uplink_end`, in the given receive window
+    pub fn downlink(&self, packet: &Packet, uplink_end: DateTime<Local>, window: ReceiveWindow) -> Downlink {
+        let mut downlink = Downlink::answer(packet, uplink_end, window, &self.downlink, self.region);
+        downlink.ack = self.confirmed;
+        downlink
+    }
+
+    /// Whether the duty cycle of the gateway lets it send the downlink
+    pub fn clear_to_send(&self, gateway_id: u32, downlink: &Downlink) -> bool {
+        self.available_at(gateway_id, downlink) <= downlink.start
+    }
+
+    /// Earliest instant from the start of the downlink the duty cycle of the gateway lets it send the downlink
+    pub fn available_at(&self, gateway_id: u32, downlink: &Downlink) -> DateTime<Local> {
+        self.gateway_duty_cycle.available_at(gateway_id, downlink.phy.frequency, downlink.start)
+    }
+
+    /// The gateway `gateway_id` is to send a downlink
+    pub fn downlink_sent(&self, gateway_id: u32, downlink: &Downlink) {
+        let time_on_air = downlink.phy.time_on_air();
+        self.gateway_duty_cycle.transmit(gateway_id, downlink.phy.frequency, downlink.start, time_on_air);
+
+        let mut state = self.inner.lock().unwrap();
+        match downlink.window {
+            ReceiveWindow::Rx1 => state.downlinks.rx1 += 1,
+            ReceiveWindow::Rx2 => state.downlinks.rx2 += 1,
+            ReceiveWindow::PingSlot | ReceiveWindow::Rxc => {}
+        }
+        state.downlinks.airtime_ms += time_on_air.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0;
+    }
+
+    /// The gateway `gateway_id` sends the beacon of a beacon period starting at `start`
+    pub fn beacon_sent(&self, gateway_id: u32, start: DateTime<Local>, beacon: &PhyParams) {
+        let time_on_air = beacon.time_on_air();
+        self.gateway_duty_cycle.transmit(gateway_id, beacon.frequency, start, time_on_air);
+        self.class_b.beacon_sent(time_on_air);
+    }
+
+    /// A downlink could be sent in neither receive window
+    pub fn downlink_missed(&self, reason: MissedDownlink) {
+        let mut state = self.inner.lock().unwrap();
+        match reason {
+            MissedDownlink::Late => state.downlinks.late += 1,
+            MissedDownlink::GatewayBusy => state.downlinks.gateway_busy += 1,
+            MissedDownlink::DutyCycle => state.downlinks.duty_cycle += 1,
+        }
+    }
+
+    /// A node hears a downlink, and applies the ADR command it carries
+    pub fn downlink_received(&self, downlink: &Downlink) {
+        let mut state = self.inner.lock().unwrap();
+        state.downlinks.received += 1;
+        if downlink.ack {
+            state.acknowledged.insert((downlink.node_id, downlink.frame_counter));
+        }
+        match downlink.window {
+            ReceiveWindow::PingSlot => self.class_b.received(),
+            ReceiveWindow::Rxc => self.class_c.received(),
+            ReceiveWindow::Rx1 | ReceiveWindow::Rx2 => {}
+        }
+        self.adr.downlink_received(downlink.node_id);
+    }
+
+    /// Whether the node of a confirmed uplink heard its acknowledgement
+    pub fn is_acknowledged(&self, packet: &Packet) -> bool {
+        self.inner.lock().unwrap().acknowledged.contains(&(packet.node_id, packet.frame_counter))
+    }
+
+    /// The node of a confirmed uplink is done with it, acknowledged or not, after transmitting `packet` last
+    pub fn confirmation(&self, packet: &Packet, acknowledged: bool) {
+        let mut state = self.inner.lock().unwrap();
+        state.confirmed.frames += 1;
+        state.confirmed.retransmissions += u64::from(packet.attempt);
+        if acknowledged {
+            state.confirmed.acknowledged += 1;
+        } else {
+            state.confirmed.unacknowledged += 1;
+        }
+        if state.frames.contains_key(&(packet.node_id, packet.frame_counter)) {
+            state.delivered_confirmed += 1;
+            state.delivered_attempts += u64::from(packet.attempt) + 1;
+        }
+        state.acknowledged.remove(&(packet.node_id, packet.frame_counter));
+    }
+
+    /// Network-wide statistics of a run seeded with `seed` that took `wall_time`, with the report of every gateway
+    /// and the uplinks the duty cycle held back
+    pub fn report(&self, seed: u64, gateways: Vec<Report>, duty_cycle: DutyCycleReport, wall_time: std::time::Duration) -> NetworkReport {
+        let state = self.inner.lock().unwrap();
+        let mut report = NetworkReport {
+            duty_cycle,
+            adr: self.adr.report(),
+            downlinks: DownlinkReport { gateway_duty_cycle: self.gateway_duty_cycle.report(), ..state.downlinks.clone() },
+            class_b: self.class_b.report(state.downlinks.airtime_ms),
+            class_c: self.class_c.report(),
+            confirmed: ConfirmedReport {
+                success_rate: ratio(state.confirmed.acknowledged, state.confirmed.frames),
+                attempts_per_delivered: state.delivered_attempts as f64 / state.delivered_confirmed.max(1) as f64,
+                ..state.confirmed.clone()
+            },
+            ..NetworkReport::new(seed, state.uplinks, state.unheard, state.frames.len() as u64, state.duplicates, gateways, wall_time)
+        };
+
+        // Gateways also deliver the retransmissions, their ratio is over every transmission
+        let transmissions = state.uplinks + state.confirmed.retransmissions;
+        for gateway in &mut report.gateway_delivery {
+            gateway.delivery_ratio = ratio(gateway.delivered, transmissions);
+        }
+        report
+    }
+}
+
+/// Gateways of a scenario sharing a network server
+#[derive(Debug, Clone)]
+pub struct Network {
+    pub gateways: Vec<Gateway>,         // Gateways in the order of the scenario
+    pub server: NetworkServer,          // Network server deduplicating the delivered packets
+    pub duty_cycle: DutyCycle,          // Airtime of the nodes in the sub-bands of the region
+    pub scenario: ScenarioConfig,       // Scenario the network is simulated in
+    pub seed: u64,                      // Master seed of the random streams of the run
+}
+
+impl Network {
+    /// Create the network of a validated scenario, drawing a seed from entropy if it has none
+    pub fn from_config(scenario: ScenarioConfig) -> Result<Self, String> {
+        let seed = scenario.seed.unwrap_or_else(rand::random);
+        let server = NetworkServer::from_config(&scenario, seed);
+        let duty_cycle = DutyCycle::new(scenario.duty_cycle_sub_bands());
+        let gateways = scenario.gateway_sites()
+            .iter()
+            .enumerate()
+            .map(|(i, site)| Gateway::from_config(&scenario, site, gateway_seed(seed, i), server.clone()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Network {
+            gateways,
+            server,
+            duty_cycle,
+            scenario,
+            seed,
+        })
+    }
+
+    /// Set up the logger and load the packets to simulate, with the instant the run starts at
+    pub fn load_packets(&self) -> (DateTime<Local>, PacketStream) {
+        setup_logger(&self.scenario.log_file, self.scenario.log_level).unwrap();
+        for gateway in &self.gateways {
+            info!("Gateway {} starts at ({:.1}, {:.1}), pending queue capacity: {}, channels: {}, allocation strategy: {}",
+                gateway.id, gateway.position.x, gateway.position.y, gateway.pending_queue_capacity,
+                gateway.channels.lock().unwrap().len(), gateway.strategy.name());
+        }
+        info!("Seed: {}", self.seed);
+        if let Some(region) = self.scenario.region {
+            info!("Region {}: max EIRP {} dBm, duty cycle {:?}", region.name(), region.max_eirp_dbm(), self.scenario.duty_cycle);
+            for band in region.sub_bands() {
+                info!("Sub-band {:.3}-{:.3} MHz: duty cycle {}%",
+                    f64::from(band.min_frequency) / 1e6, f64::from(band.max_frequency) / 1e6, band.duty_cycle * 100.0);
+            }
+        }
+
+        // Generated packets start the run at the first arrival, as a loaded trace does
+        if self.scenario.traffic.generate {
+            let mut packets = match generate_random_packet(&self.scenario, self.seed) {
+                Ok(packets) => packets.peekable(),
+                Err(e) => {
+                    error!("Failed to generate packets: {}", e);
+                    panic!("Failed to generate packets: {}", e);
+                }
+            };
+            let start = packets.peek().map_or_else(Local::now, |p| p.arrival_time);
+            return (start, Box::new(packets));
+        }
+
+        match load_data(&self.scenario.packet_file, &self.scenario.replay) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                error!("Failed to load packets: {}", e);
+                panic!("Failed to load packets: {}", e);
+            }
+        }
+    }
+
+    /// Coverage of the gateways, from the positions of the node file when the scenario lists gateways
+    pub fn coverage(&self) -> Coverage {
+        let nodes = if self.scenario.network.gateways.is_empty() {
+            Vec::new()
+        } else {
+            load_nodes(&self.scenario.node_file).unwrap_or_else(|e| {
+                warn!("No node positions in {} ({}), packets are received at their stored RSSI", self.scenario.node_file, e);
+                Vec::new()
+            })
+        };
+        Coverage::new(self.scenario.network.propagation.clone(), nodes, self.seed)
+    }
+
+    /// Send the uplinks with the spreading factor and power the network server chose for their nodes, when it adapts them
+    pub fn adapt(&self, packets: PacketStream) -> PacketStream {
+        if !self.server.adr.is_enabled() {
+            return packets;
+        }
+        Box::new(AdaptedUplinks::new(packets, self.server.adr.clone()))
+    }
+
+    /// Hold the uplinks to the duty cycle of the sub-bands of the region, unless the scenario turns it off
+    ///
+    /// Nodes of the node file may move an uplink to another channel of their plan whose sub-band is available
+    pub fn regulate(&self, packets: PacketStream) -> PacketStream {
+        if self.scenario.duty_cycle_sub_bands().is_empty() {
+            return packets;
+        }
+
+        Box::new(RegulatedUplinks::new(packets, self.channel_plans(), self.duty_cycle.clone(), self.scenario.duty_cycle))
+    }
+
+    /// Retransmitter of the unacknowledged confirmed uplinks, `None` unless the uplinks are confirmed
+    pub fn retransmitter(&self) -> Option<Retransmitter> {
+        let config = &self.scenario.network.confirmed;
+        if !config.enabled {
+            return None;
+        }
+        Some(Retransmitter::new(config.clone(), self.channel_plans(), self.duty_cycle.clone(), self.scenario.duty_cycle, self.seed))
+    }
+
+    /// Class B of the network server, `None` unless the nodes open ping slots
+    pub fn class_b(&self) -> Option<ClassB> {
+        self.server.class_b.is_enabled().then(|| self.server.class_b.clone())
+    }
+
+    /// Class C of the network server, `None` unless the nodes listen continuously
+    pub fn class_c(&self) -> Option<ClassC> {
+        self.server.class_c.is_enabled().then(|| self.server.class_c.clone())
+    }
+
+    /// Channel plan of each node of the node file, none when it cannot be loaded
+    fn channel_plans(&self) -> HashMap<u32, Vec<u32>> {
+        let nodes = load_nodes(&self.scenario.node_file).unwrap_or_else(|e| {
+            warn!("No channel plans in {} ({}), uplinks keep the channel they were sent on", self.scenario.node_file, e);
+            Vec::new()
+        });
+        nodes.into_iter().map(|node| (node.id, node.radio.channels)).collect()
+    }
+
+    /// Trace recorder for a run starting at the given instant, disabled unless a trace file is configured
+    pub fn trace_for_run(&self, origin: DateTime<Local>) -> Trace {
+        if self.scenario.trace_csv.is_some() || self.scenario.trace_jsonl.is_some() {
+            Trace::new(origin)
+        } else {
+            Trace::disabled()
+        }
+    }
+
+    /// Print the statistics of a run that took `wall_time` and write them to the configured file
+    pub fn report_statistics(&self, start: DateTime<Local>, end: DateTime<Local>, wall_time: std::time::Duration) {
+        let gateways = self.gateways.iter().map(|gateway| gateway.stats.report(gateway.id, start, end)).collect();
+        let report = self.server.report(self.seed, gateways, self.duty_cycle.report(), wall_time);
+        report.print_table();
+
+        if let Some(path) = &self.scenario.statistics_file {
+            if let Err(e) = write_statistics(&report, path) {
+                error!("Failed to write statistics to {}: {}", path, e);
+            }
+        }
+    }
+
+    /// Write the recorded trace to the configured files
+    pub fn export_trace(&self, trace: &Trace) {
+        let records = trace.records();
+
+        if let Some(path) = &self.scenario.trace_csv {
+            if let Err(e) = write_trace_csv(&records, path) {
+                error!("Failed to write trace to {}: {}", path, e);
+            }
+        }
+        if let Some(path) = &self.scenario.trace_jsonl {
+            if let Err(e) = write_trace_jsonl(&records, path) {
+                error!("Failed to write trace to {}: {}", path, e);
+            }
+        }
+    }
+
+    /// Run the simulation in the mode of the scenario
+    pub fn simulation(&self) {
+        match self.scenario.mode {
+            SimulationMode::EventDriven => self.run_event_driven(),
+            SimulationMode::RealTime => self.run_real_time(),
+        }
+    }
+
+    /// Run the simulation as discrete events on a virtual clock shared by every gateway
+    pub fn run_event_driven(&self) {
+        let start_time = Instant::now();
+        let (start, packet_queue) = self.load_packets();
+        let mut packet_queue = self.regulate(self.adapt(packet_queue)).peekable();
+
+        if packet_queue.peek().is_none() {
+            println!("No packets to simulate");
+            return;
+        }
+
+        let clock = Clock::virtual_at(start);
+        let trace = self.trace_for_run(start);
+        let gateways = self.gateways
+            .iter()
+            .map(|gateway| {
+                let mut gateway = gateway.clone();
+                gateway.clock = clock.clone();
+                gateway.trace = trace.for_gateway(gateway.id);
+                gateway.pending_queue.lock().unwrap().clear();
+                gateway
+            })
+            .collect();
+
+        let mut engine = EventEngine::new(gateways, self.server.clone(), self.coverage(), self.retransmitter(), self.class_b(),
+            self.class_c());
+        engine.schedule_arrivals(Box::new(packet_queue));
+        let end = engine.run();
+
+        info!("All packets are processed");
+        self.export_trace(&trace);
+        self.report_statistics(start, end, start_time.elapsed());
+
+        println!("Total processing time: {:?}", start_time.elapsed());
+    }
+
+    /// Run the simulation of the single gateway in real time with one thread per channel
+    pub fn run_real_time(&self) {
+        let start_time = Instant::now();
+        let (origin, arrivals) = self.load_packets();
+        let mut arrivals = self.regulate(self.adapt(arrivals)).peekable();
+        if arrivals.peek().is_none() {
+            println!("No packets to simulate");
+            return;
+        }
+
+        let start = Local::now();
+        let trace = self.trace_for_run(start);
+        let mut gateway = self.gateways[0].clone();
+        gateway.trace = trace.for_gateway(gateway.id);
+        gateway.pending_queue.lock().unwrap().clear();
+        let end = gateway.run_real_time(arrivals, origin, start, self.coverage());
+
+        info!("All packets are processed, waiting for channels to finish");
+        self.export_trace(&trace);
+        self.report_statistics(start, end, start_time.elapsed());
+
+        let elapsed = start_time.elapsed();
+        println!("Total processing time: {:?}", elapsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::LevelFilter;
+    use std::fs;
+
+    /// Statistics of a run of the scenario in a directory of its own, without the wall-clock measures
+    fn run(mut scenario: ScenarioConfig, name: &str) -> serde_json::Value {
+        let dir = std::env::temp_dir().join(format!("lorasim-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let path = |file: &str| dir.join(file).to_string_lossy().into_owned();
+
+        scenario.packet_file = path("packet.json");
+        scenario.node_file = path("nodes.json");
+        scenario.log_file = path("simulation.log");
+        scenario.log_level = LevelFilter::Error;
+        scenario.statistics_file = Some(path("statistics.json"));
+        scenario.validate().unwrap();
+        Network::from_config(scenario).unwrap().simulation();
+
+        let mut report: serde_json::Value = serde_json::from_str(&fs::read_to_string(path("statistics.json")).unwrap()).unwrap();
+        let fields = report.as_object_mut().unwrap();
+        fields.remove("wall_time_ms");
+        fields.remove("peak_memory_mb");
+        fs::remove_dir_all(&dir).unwrap();
+        report
+    }
+
+    /// Generated traffic of 50 nodes around two gateways at the given positions
+    fn scenario(seed: u64, gateways: [f64; 2]) -> ScenarioConfig {
+        let mut scenario = ScenarioConfig { seed: Some(seed), ..ScenarioConfig::default() };
+        scenario.traffic.generate = true;
+        scenario.traffic.packet_count = 500;
+        scenario.traffic.node_count = 50;
+        scenario.network.gateways = vec![
+            GatewaySite { id: 1, position: Position { x: gateways[0], y: 0.0 } },
+            GatewaySite { id: 2, position: Position { x: gateways[1], y: 0.0 } },
+        ];
+        scenario
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_report() {
+        // Draw on most random streams
+        let scenario = |seed| {
+            let mut scenario = scenario(seed, [-300.0, 300.0]);
+            scenario.network.adr.enabled = true;
+            scenario.network.adr.history = 3;
+            scenario.network.class_b.enabled = true;
+            scenario.network.class_b.downlink_interval_s = 10.0;
+            scenario
+        };
+        let first = run(scenario(7), "first");
+        assert_eq!(first, run(scenario(7), "second"));
+        assert_ne!(first, run(scenario(8), "other"));
+    }
+
+    #[test]
+    fn uplink_heard_by_two_gateways_is_delivered_once() {
+        // Gateways at the same place hear and serve the same uplinks
+        let report = run(scenario(1, [0.0, 0.0]), "colocated");
+        let delivered = report["delivered"].as_u64().unwrap();
+        assert!(delivered > 0);
+        assert_eq!(report["duplicates"].as_u64(), Some(delivered));
+        for gateway in report["gateway_delivery"].as_array().unwrap() {
+            assert_eq!(gateway["delivered"].as_u64(), Some(delivered));
+            assert_eq!(gateway["delivery_ratio"], report["delivery_ratio"]);
+        }
+    }
+
+    #[test]
+    fn each_gateway_is_credited_with_its_own_deliveries() {
+        let report = run(scenario(1, [-600.0, 600.0]), "apart");
+        let uplinks = report["uplinks"].as_u64().unwrap() as f64;
+        let gateways: Vec<u64> = report["gateway_delivery"].as_array().unwrap()
+            .iter()
+            .map(|gateway| {
+                let delivered = gateway["delivered"].as_u64().unwrap();
+                assert_eq!(gateway["delivery_ratio"].as_f64(), Some(delivered as f64 / uplinks));
+                delivered
+            })
+            .collect();
+        let delivered = report["delivered"].as_u64().unwrap();
+        let duplicates = report["duplicates"].as_u64().unwrap();
+        assert_eq!(gateways.iter().sum::<u64>(), delivered + duplicates);
+        assert!(gateways.iter().all(|&own| own <= delivered));
+        assert!(duplicates < delivered, "gateways apart hear different uplinks");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,201 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::models::arrival::{ArrivalProcess, ArrivalSampler};
+use crate::models::packet::Packet;
+use crate::models::phy::PhyParams;
+
+use chrono::{DateTime, Local, Duration};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Position on the deployment plane in metres
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: f64,                     // East of the origin
+    pub y: f64,                     // North of the origin
+}
+
+impl Position {
+    /// Straight-line distance to another position in metres
+    pub fn distance(&self, other: &Position) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+/// Radio settings of a node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RadioSettings {
+    pub spreading_factor: u8,       // Spreading factor of the uplinks
+    pub tx_power_dbm: f64,          // Transmission power
+    pub bandwidth: u32,             // Bandwidth in Hz
+    pub coding_rate: u8,            // Coding rate 4/(4 + coding_rate)
+    pub channels: Vec<u32>,         // Channel plan, uplink frequencies in Hz one is drawn from per uplink
+}
+
+/// State of a node, evolving as it transmits
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodeState {
+    pub frame_counter: u32,         // Frame counter of the next uplink
+    pub remaining: u64,             // Uplinks still to send
+    #[serde(skip)]
+    pub busy_until: Option<DateTime<Local>>, // End of the uplink on air, the radio sends one at a time
+}
+
+/// End device transmitting uplinks to the gateways
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Node {
+    pub id: u32,                    // Node ID
+    pub position: Position,         // Where the node is deployed
+    pub traffic: ArrivalProcess,    // Traffic profile, the process its uplinks follow
+    pub radio: RadioSettings,       // Radio settings of its uplinks
+    pub state: NodeState,           // Frame counter and uplinks left
+}
+
+impl Node {
+    /// Build the next uplink of the node, on a frequency of its channel plan
+    pub fn uplink<R: Rng>(&mut self, packet_id: u32, arrival_time: DateTime<Local>, payload_length: u8, rng: &mut R) -> Packet {
+        let phy = PhyParams {
+            frequency: self.radio.channels[rng.gen_range(0..self.radio.channels.len())],
+            spreading_factor: self.radio.spreading_factor,
+            bandwidth: self.radio.bandwidth,
+            coding_rate: self.radio.coding_rate,
+            payload_length,
+            ..PhyParams::default()
+        };
+
+        let mut packet = Packet::from_phy(packet_id, phy, arrival_time);
+        packet.node_id = self.id;
+        packet.frame_counter = self.state.frame_counter;
+        packet.tx_power_dbm = Some(self.radio.tx_power_dbm);
+        self.state.frame_counter = self.state.frame_counter.wrapping_add(1);
+        self.state.remaining = self.state.remaining.saturating_sub(1);
+        self.state.busy_until = Some(arrival_time + packet.phy.time_on_air());
+        packet
+    }
+}
+
+/// Population of nodes yielding their uplinks in arrival order, one at a time
+#[derive(Debug)]
+pub struct NodePopulation {
+    nodes: Vec<Node>,               // Nodes of the population, indexed by position in the vector
+    samplers: Vec<ArrivalSampler>,  // Arrival times of the uplinks of each node
+    upcoming: BinaryHeap<Reverse<(Duration, usize)>>, // Next arrival offset of each node with uplinks left
+    epoch: DateTime<Local>,         // Instant the arrival offsets are counted from
+    payload_length: [u8; 2],        // Inclusive range of payload lengths in bytes
+    rssi: [f64; 2],                 // Inclusive range of received power in dBm
+    rng: ChaCha8Rng,                // Traffic stream, draws the frequency, payload length and RSSI
+    arrival_rng: ChaCha8Rng,        // Arrival stream, draws the arrival times
+    next_id: u32,                   // ID of the next packet
+}
+
+impl NodePopulation {
+    /// Population whose arrival offsets are counted from `epoch`
+    pub fn new(nodes: Vec<Node>, epoch: DateTime<Local>, payload_length: [u8; 2], rssi: [f64; 2],
+        rng: ChaCha8Rng, mut arrival_rng: ChaCha8Rng) -> Self {
+        let mut samplers: Vec<ArrivalSampler> = nodes
+            .iter()
+            .map(|node| ArrivalSampler::new(node.traffic.clone(), node.state.remaining))
+            .collect();
+        let upcoming = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.state.remaining > 0)
+            .map(|(i, _)| Reverse((samplers[i].next_offset(&mut arrival_rng), i)))
+            .collect();
+
+        NodePopulation {
+            nodes,
+            samplers,
+            upcoming,
+            epoch,
+            payload_length,
+            rssi,
+            rng,
+            arrival_rng,
+            next_id: 0,
+        }
+    }
+}
+
+impl Iterator for NodePopulation {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        let (arrival_time, i) = loop {
+            let Reverse((offset, i)) = self.upcoming.pop()?;
+            let arrival_time = self.epoch + offset;
+
+            // An uplink due while the previous one of its node is on air waits for the radio to be free
+            match self.nodes[i].state.busy_until {
+                Some(busy_until) if busy_until > arrival_time => self.upcoming.push(Reverse((busy_until - self.epoch, i))),
+                _ => break (arrival_time, i),
+            }
+        };
+
+        let payload_length = self.rng.gen_range(self.payload_length[0]..=self.payload_length[1]);
+        let mut packet = self.nodes[i].uplink(self.next_id, arrival_time, payload_length, &mut self.rng);
+        packet.rssi = self.rng.gen_range(self.rssi[0]..=self.rssi[1]);
+        self.next_id += 1;
+
+        if self.nodes[i].state.remaining > 0 {
+            let next_offset = self.samplers[i].next_offset(&mut self.arrival_rng);
+            self.upcoming.push(Reverse((next_offset, i)));
+        }
+        Some(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::rng::{stream_rng, RngStream};
+
+    fn node(id: u32, spreading_factor: u8, rate: f64, remaining: u64) -> Node {
+        Node {
+            id,
+            position: Position::default(),
+            traffic: ArrivalProcess::Poisson { rate },
+            radio: RadioSettings { spreading_factor, tx_power_dbm: 14.0, bandwidth: 125_000, coding_rate: 1, channels: vec![868_100_000] },
+            state: NodeState { remaining, ..NodeState::default() },
+        }
+    }
+
+    fn population(nodes: Vec<Node>) -> NodePopulation {
+        NodePopulation::new(nodes, Local::now(), [20, 20], [-100.0, -100.0], stream_rng(1, RngStream::Traffic), stream_rng(1, RngStream::Arrival))
+    }
+
+    #[test]
+    fn node_waits_for_its_previous_uplink_to_end() {
+        // A thousand uplinks per second is far more than an SF12 radio can send
+        let packets: Vec<Packet> = population(vec![node(1, 12, 1000.0, 20)]).collect();
+        assert_eq!(packets.len(), 20);
+        for pair in packets.windows(2) {
+            assert!(pair[1].arrival_time >= pair[0].arrival_time + pair[0].phy.time_on_air());
+            assert_eq!(pair[1].frame_counter, pair[0].frame_counter + 1);
+        }
+    }
+
+    #[test]
+    fn population_yields_uplinks_in_arrival_order() {
+        let packets: Vec<Packet> = population(vec![node(1, 12, 100.0, 50), node(2, 7, 10.0, 50)]).collect();
+        assert_eq!(packets.len(), 100);
+        assert!(packets.windows(2).all(|pair| pair[0].arrival_time <= pair[1].arrival_time));
+    }
+}
\ No newline at end of file
@@ -15,6 +15,8 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use crate::models::phy::PhyParams;
+
 use chrono::{DateTime, Local, Duration};
 use log::info;
 use serde::{Serialize, Deserialize};
@@ -22,18 +24,54 @@ use serde::{Serialize, Deserialize};
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Packet {
     pub packet_id: u32,                // Packet ID
+    #[serde(default)]
+    pub node_id: u32,                  // ID of the node that sent the packet
+    #[serde(default)]
+    pub frame_counter: u32,            // Frame counter of the uplink at its node
     pub arrival_time: DateTime<Local>, // Arrival time
     pub processing_time: Duration,     // Processing time required
+    #[serde(flatten)]
+    pub phy: PhyParams,                // LoRa physical layer parameters
+    #[serde(default)]
+    pub tx_power_dbm: Option<f64>,     // Transmission power, that of its node when absent
+    #[serde(default = "default_rssi")]
+    pub rssi: f64,                     // Received signal strength at the gateway in dBm
+    #[serde(skip)]
+    pub snr: f64,                      // Signal-to-noise ratio at the gateway in dB, computed on reception
+    #[serde(skip)]
+    pub uplink_end: Option<DateTime<Local>>, // End of the transmission, from which the receive windows of its node open
+    #[serde(default)]
+    pub retries: u32,                  // Times the packet was resent after timing out
+    #[serde(skip)]
+    pub attempt: u32,                  // Transmissions of the same frame by its node before this one
+}
+
+/// Packets in arrival order, produced as the simulation consumes them
+pub type PacketStream = Box<dyn Iterator<Item = Packet>>;
+
+/// RSSI of packets stored without one
+fn default_rssi() -> f64 {
+    -100.0
 }
 
 impl Packet {
-    pub fn new(packet_id: u32, processing_time: Duration) -> Self {
-        let local_now = Local::now();
-        info!("Packet created: {:?},\n\tArrival time: {:?},\n\tProcessing time: {:?} ms", packet_id, local_now, processing_time.num_milliseconds());
+    /// Create a packet arriving at `arrival_time` whose processing time is the time on air of its PHY parameters
+    pub fn from_phy(packet_id: u32, phy: PhyParams, arrival_time: DateTime<Local>) -> Self {
+        info!("Packet {}: SF{}, {} kHz, CR 4/{}, {} bytes", packet_id, phy.spreading_factor,
+            phy.bandwidth / 1000, phy.coding_rate + 4, phy.payload_length);
         Packet {
             packet_id,
-            arrival_time: local_now,
-            processing_time,
+            node_id: 0,
+            frame_counter: 0,
+            arrival_time,
+            processing_time: phy.time_on_air(),
+            phy,
+            tx_power_dbm: None,
+            rssi: default_rssi(),
+            snr: 0.0,
+            uplink_end: None,
+            retries: 0,
+            attempt: 0,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,135 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use chrono::Duration;
+use serde::{Serialize, Deserialize};
+
+/// Supply voltage of the radio in volts
+const SUPPLY_VOLTAGE: f64 = 3.0;
+
+/// Supply current of an SX1272 transmitting at -2 to 20 dBm in mA, as measured for LoRaSim
+const TX_CURRENT_MA: [f64; 23] = [
+    22.0, 22.0, 22.0, 23.0, 24.0, 24.0, 24.0, 25.0, 25.0, 25.0, 25.0, 26.0,
+    31.0, 32.0, 34.0, 35.0, 44.0, 82.0, 85.0, 90.0, 105.0, 115.0, 125.0,
+];
+
+/// Energy a radio draws from its supply to transmit at `tx_power_dbm` for `time_on_air`, in mJ
+pub fn transmit_energy_mj(tx_power_dbm: f64, time_on_air: Duration) -> f64 {
+    let index = (tx_power_dbm.round() + 2.0).clamp(0.0, (TX_CURRENT_MA.len() - 1) as f64) as usize;
+    let seconds = time_on_air.num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+    TX_CURRENT_MA[index] * SUPPLY_VOLTAGE * seconds
+}
+
+/// LoRa physical layer parameters of a transmission
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PhyParams {
+    pub frequency: u32,         // Carrier frequency in Hz
+    pub spreading_factor: u8,   // Spreading factor, 7 to 12
+    pub bandwidth: u32,         // Bandwidth in Hz, e.g. 125000
+    pub coding_rate: u8,        // Coding rate 4/(4 + coding_rate), 1 to 4
+    pub payload_length: u8,     // Payload length in bytes
+    pub preamble_length: u16,   // Number of programmed preamble symbols
+    pub explicit_header: bool,  // Whether the PHY header is transmitted
+    pub crc: bool,              // Whether the payload CRC is transmitted
+}
+
+impl Default for PhyParams {
+    fn default() -> Self {
+        PhyParams {
+            frequency: 868_100_000,
+            spreading_factor: 7,
+            bandwidth: 125_000,
+            coding_rate: 1,
+            payload_length: 20,
+            preamble_length: 8,
+            explicit_header: true,
+            crc: true,
+        }
+    }
+}
+
+impl PhyParams {
+    /// Check that the parameters describe a LoRa transmission, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        if !(7..=12).contains(&self.spreading_factor) {
+            return Err(format!("spreading_factor must be within 7 to 12, got {}", self.spreading_factor));
+        }
+        if ![125_000, 250_000, 500_000].contains(&self.bandwidth) {
+            return Err(format!("bandwidth must be 125000, 250000 or 500000 Hz, got {}", self.bandwidth));
+        }
+        if !(1..=4).contains(&self.coding_rate) {
+            return Err(format!("coding_rate must be within 1 to 4, got {}", self.coding_rate));
+        }
+        Ok(())
+    }
+
+    /// Duration of one symbol in seconds
+    pub fn symbol_time(&self) -> f64 {
+        f64::from(1u32 << self.spreading_factor) / f64::from(self.bandwidth)
+    }
+
+    /// Low data rate optimisation is mandated when a symbol lasts 16 ms or longer
+    pub fn low_data_rate_optimize(&self) -> bool {
+        self.symbol_time() >= 0.016
+    }
+
+    /// Number of payload symbols, including the header and CRC
+    pub fn payload_symbols(&self) -> f64 {
+        let sf = f64::from(self.spreading_factor);
+        let crc = if self.crc { 1.0 } else { 0.0 };
+        let implicit_header = if self.explicit_header { 0.0 } else { 1.0 };
+        let ldro = if self.low_data_rate_optimize() { 1.0 } else { 0.0 };
+
+        let numerator = 8.0 * f64::from(self.payload_length) - 4.0 * sf + 28.0 + 16.0 * crc - 20.0 * implicit_header;
+        let denominator = 4.0 * (sf - 2.0 * ldro);
+        let blocks = (numerator / denominator).ceil() * f64::from(self.coding_rate + 4);
+
+        8.0 + blocks.max(0.0)
+    }
+
+    /// Time on air following the Semtech SX127x formula
+    pub fn time_on_air(&self) -> Duration {
+        let preamble = (f64::from(self.preamble_length) + 4.25) * self.symbol_time();
+        let payload = self.payload_symbols() * self.symbol_time();
+        Duration::microseconds(((preamble + payload) * 1e6).round() as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phy(spreading_factor: u8, payload_length: u8) -> PhyParams {
+        PhyParams { spreading_factor, payload_length, ..PhyParams::default() }
+    }
+
+    #[test]
+    fn time_on_air_matches_semtech_calculator() {
+        // 125 kHz, CR 4/5, 8 preamble symbols, explicit header and CRC, as in the Semtech LoRa calculator
+        assert_eq!(phy(7, 20).time_on_air(), Duration::microseconds(56_576));
+        assert_eq!(phy(9, 51).time_on_air(), Duration::microseconds(328_704));
+        assert_eq!(phy(12, 20).time_on_air(), Duration::microseconds(1_318_912));
+    }
+
+    #[test]
+    fn low_data_rate_optimize_from_sf11_at_125_khz() {
+        assert!(!phy(10, 20).low_data_rate_optimize());
+        assert!(phy(11, 20).low_data_rate_optimize());
+        assert!(!PhyParams { bandwidth: 250_000, ..phy(11, 20) }.low_data_rate_optimize());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,292 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+/// Names of the path loss models, as given in a scenario
+pub const PATH_LOSS_MODEL_NAMES: [&str; 4] = ["free-space", "log-distance", "okumura-hata", "cost-231"];
+
+/// Surroundings of the link in the Okumura-Hata model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Environment {
+    #[default]
+    Urban,                          // Small or medium city
+    Suburban,                       // Suburban area
+    Rural,                          // Open area
+}
+
+/// Model of the path loss between a node and a gateway
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum PathLossModel {
+    FreeSpace,                      // Friis free-space loss at the carrier frequency
+    LogDistance {
+        #[serde(default = "default_reference_distance")]
+        reference_distance_m: f64,  // Distance the reference loss is measured at
+        #[serde(default = "default_reference_loss")]
+        reference_loss_db: f64,     // Path loss at the reference distance
+        #[serde(default = "default_exponent")]
+        exponent: f64,              // Growth of the path loss with the logarithm of the distance
+    },
+    OkumuraHata {
+        #[serde(default)]
+        environment: Environment,   // Urban, suburban or rural surroundings
+        #[serde(default = "default_gateway_height")]
+        gateway_height_m: f64,      // Antenna height of the gateway, 30 to 200 m
+        #[serde(default = "default_node_height")]
+        node_height_m: f64,         // Antenna height of the node, 1 to 10 m
+    },
+    #[serde(rename = "cost-231")]
+    Cost231 {
+        #[serde(default)]
+        metropolitan: bool,         // Dense city centre, adds 3 dB
+        #[serde(default = "default_gateway_height")]
+        gateway_height_m: f64,      // Antenna height of the gateway, 30 to 200 m
+        #[serde(default = "default_node_height")]
+        node_height_m: f64,         // Antenna height of the node, 1 to 10 m
+    },
+}
+
+// Measurements of Bor et al. in a built-up area, as used by LoRaSim
+fn default_reference_distance() -> f64 { 40.0 }
+fn default_reference_loss() -> f64 { 127.41 }
+fn default_exponent() -> f64 { 2.08 }
+fn default_gateway_height() -> f64 { 30.0 }
+fn default_node_height() -> f64 { 1.5 }
+
+impl Default for PathLossModel {
+    fn default() -> Self {
+        PathLossModel::LogDistance {
+            reference_distance_m: default_reference_distance(),
+            reference_loss_db: default_reference_loss(),
+            exponent: default_exponent(),
+        }
+    }
+}
+
+impl PathLossModel {
+    /// Model of the given name with its default parameters
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "free-space" => Some(PathLossModel::FreeSpace),
+            "log-distance" => Some(PathLossModel::default()),
+            "okumura-hata" => Some(PathLossModel::OkumuraHata {
+                environment: Environment::default(),
+                gateway_height_m: default_gateway_height(),
+                node_height_m: default_node_height(),
+            }),
+            "cost-231" => Some(PathLossModel::Cost231 {
+                metropolitan: false,
+                gateway_height_m: default_gateway_height(),
+                node_height_m: default_node_height(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Check the parameters, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |value: f64| value.is_finite() && value > 0.0;
+        match self {
+            PathLossModel::FreeSpace => {}
+            PathLossModel::LogDistance { reference_distance_m, reference_loss_db, exponent } => {
+                if !positive(*reference_distance_m) {
+                    return Err(format!("reference_distance_m must be positive, got {}", reference_distance_m));
+                }
+                if !reference_loss_db.is_finite() {
+                    return Err(String::from("reference_loss_db must be a finite number"));
+                }
+                if !positive(*exponent) {
+                    return Err(format!("exponent must be positive, got {}", exponent));
+                }
+            }
+            PathLossModel::OkumuraHata { gateway_height_m, node_height_m, .. }
+            | PathLossModel::Cost231 { gateway_height_m, node_height_m, .. } => {
+                if !positive(*gateway_height_m) {
+                    return Err(format!("gateway_height_m must be positive, got {}", gateway_height_m));
+                }
+                if !positive(*node_height_m) {
+                    return Err(format!("node_height_m must be positive, got {}", node_height_m));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Path loss in dB over a distance in metres at a carrier frequency in Hz
+    ///
+    /// Distances below 1 m, or below the reference distance of the log-distance model, count as that distance
+    pub fn path_loss_db(&self, distance_m: f64, frequency: u32) -> f64 {
+        let distance_m = distance_m.max(1.0);
+        let f_mhz = f64::from(frequency) / 1e6;
+        match self {
+            PathLossModel::FreeSpace => {
+                20.0 * distance_m.log10() + 20.0 * f_mhz.log10() - 27.55
+            }
+            PathLossModel::LogDistance { reference_distance_m, reference_loss_db, exponent } => {
+                let distance_m = distance_m.max(*reference_distance_m);
+                reference_loss_db + 10.0 * exponent * (distance_m / reference_distance_m).log10()
+            }
+            PathLossModel::OkumuraHata { environment, gateway_height_m, node_height_m } => {
+                let urban = hata_urban_loss(69.55, 26.16, distance_m, f_mhz, *gateway_height_m, *node_height_m);
+                match environment {
+                    Environment::Urban => urban,
+                    Environment::Suburban => urban - 2.0 * (f_mhz / 28.0).log10().powi(2) - 5.4,
+                    Environment::Rural => urban - 4.78 * f_mhz.log10().powi(2) + 18.33 * f_mhz.log10() - 40.94,
+                }
+            }
+            PathLossModel::Cost231 { metropolitan, gateway_height_m, node_height_m } => {
+                let loss = hata_urban_loss(46.3, 33.9, distance_m, f_mhz, *gateway_height_m, *node_height_m);
+                if *metropolitan { loss + 3.0 } else { loss }
+            }
+        }
+    }
+}
+
+/// Hata loss of a small or medium city with the given constant and frequency coefficient
+fn hata_urban_loss(constant: f64, frequency_coefficient: f64, distance_m: f64, f_mhz: f64, gateway_height_m: f64, node_height_m: f64) -> f64 {
+    let node_height_correction = (1.1 * f_mhz.log10() - 0.7) * node_height_m - (1.56 * f_mhz.log10() - 0.8);
+    constant + frequency_coefficient * f_mhz.log10() - 13.82 * gateway_height_m.log10() - node_height_correction
+        + (44.9 - 6.55 * gateway_height_m.log10()) * (distance_m / 1000.0).log10()
+}
+
+/// Received power and signal-to-noise ratio of a transmission at a gateway
+#[derive(Debug, Clone, Copy)]
+pub struct Link {
+    pub rssi: f64,                  // Received power in dBm
+    pub snr: f64,                   // Signal-to-noise ratio in dB
+}
+
+/// Propagation between the nodes and the gateways, decides which gateways hear a transmission
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Propagation {
+    pub path_loss: PathLossModel,   // Model of the mean path loss
+    pub shadowing_sigma_db: f64,    // Standard deviation of the log-normal shadowing of each link, none when 0
+    pub noise_figure_db: f64,       // Noise figure of the gateway receiver
+    pub sensitivity_dbm: [f64; 6],  // Weakest received power demodulated at SF7 to SF12 with 125 kHz
+}
+
+impl Default for Propagation {
+    // Sensitivities of the Semtech SX1276 datasheet
+    fn default() -> Self {
+        Propagation {
+            path_loss: PathLossModel::default(),
+            shadowing_sigma_db: 0.0,
+            noise_figure_db: 6.0,
+            sensitivity_dbm: [-123.0, -126.0, -129.0, -132.0, -134.5, -137.0],
+        }
+    }
+}
+
+impl Propagation {
+    /// Check that every parameter is usable, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        self.path_loss.validate().map_err(|e| format!("path_loss.{}", e))?;
+        if !self.shadowing_sigma_db.is_finite() || self.shadowing_sigma_db < 0.0 {
+            return Err(format!("shadowing_sigma_db must not be negative, got {}", self.shadowing_sigma_db));
+        }
+        if !self.noise_figure_db.is_finite() {
+            return Err(String::from("noise_figure_db must be a finite number"));
+        }
+        if !self.sensitivity_dbm.iter().all(|s| s.is_finite()) {
+            return Err(format!("sensitivity_dbm must be finite numbers, got {:?}", self.sensitivity_dbm));
+        }
+        Ok(())
+    }
+
+    /// Thermal noise power in dBm over a bandwidth in Hz, including the noise figure
+    pub fn noise_floor_dbm(&self, bandwidth: u32) -> f64 {
+        -174.0 + 10.0 * f64::from(bandwidth).log10() + self.noise_figure_db
+    }
+
+    /// Sensitivity of a spreading factor, raised with the noise for bandwidths wider than 125 kHz
+    pub fn sensitivity_for(&self, spreading_factor: u8, bandwidth: u32) -> f64 {
+        let index = usize::from(spreading_factor.clamp(7, 12) - 7);
+        self.sensitivity_dbm[index] + 10.0 * (f64::from(bandwidth) / 125_000.0).log10()
+    }
+
+    /// Link of a transmission received at `rssi`, `None` below the sensitivity of its spreading factor
+    pub fn link(&self, rssi: f64, spreading_factor: u8, bandwidth: u32) -> Option<Link> {
+        if rssi < self.sensitivity_for(spreading_factor, bandwidth) {
+            return None;
+        }
+        Some(Link { rssi, snr: rssi - self.noise_floor_dbm(bandwidth) })
+    }
+
+    /// Shadowing of a link drawn from its log-normal distribution
+    pub fn shadowing_db<R: Rng>(&self, rng: &mut R) -> f64 {
+        if self.shadowing_sigma_db == 0.0 {
+            return 0.0;
+        }
+        self.shadowing_sigma_db * standard_normal(rng)
+    }
+}
+
+/// Standard normal sample, by the Box-Muller transform
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 0.1, "path loss {} dB, expected {} dB", actual, expected);
+    }
+
+    fn hata(environment: Environment) -> PathLossModel {
+        PathLossModel::OkumuraHata { environment, gateway_height_m: 30.0, node_height_m: 1.5 }
+    }
+
+    #[test]
+    fn free_space_matches_friis() {
+        // 32.44 + 20 log10(d km) + 20 log10(f MHz)
+        assert_close(PathLossModel::FreeSpace.path_loss_db(1000.0, 868_000_000), 91.2);
+        assert_close(PathLossModel::FreeSpace.path_loss_db(10_000.0, 868_000_000), 111.2);
+    }
+
+    #[test]
+    fn log_distance_matches_lorasim() {
+        // Bor et al.: 127.41 dB at 40 m with an exponent of 2.08
+        let model = PathLossModel::default();
+        assert_close(model.path_loss_db(40.0, 868_000_000), 127.41);
+        assert_close(model.path_loss_db(400.0, 868_000_000), 148.21);
+        assert_close(model.path_loss_db(10.0, 868_000_000), 127.41);
+    }
+
+    #[test]
+    fn okumura_hata_matches_reference_values() {
+        // 900 MHz, 30 m base station, 1.5 m mobile
+        assert_close(hata(Environment::Urban).path_loss_db(1000.0, 900_000_000), 126.4);
+        assert_close(hata(Environment::Urban).path_loss_db(10_000.0, 900_000_000), 161.6);
+        assert_close(hata(Environment::Suburban).path_loss_db(1000.0, 900_000_000), 116.5);
+        assert_close(hata(Environment::Rural).path_loss_db(1000.0, 900_000_000), 97.9);
+    }
+
+    #[test]
+    fn cost_231_matches_reference_values() {
+        // 1800 MHz, 30 m base station, 1.5 m mobile
+        let model = |metropolitan| PathLossModel::Cost231 { metropolitan, gateway_height_m: 30.0, node_height_m: 1.5 };
+        assert_close(model(false).path_loss_db(1000.0, 1_800_000_000), 136.2);
+        assert_close(model(true).path_loss_db(1000.0, 1_800_000_000), 139.2);
+    }
+}
\ No newline at end of file
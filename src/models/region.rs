@@ -0,0 +1,253 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::models::channel::ChannelConfig;
+
+use serde::{Serialize, Deserialize};
+
+/// Names of the regions, as given in a scenario
+pub const REGION_NAMES: [&str; 6] = ["EU868", "US915", "AS923", "CN470", "AU915", "IN865"];
+
+/// LoRaWAN region, whose regional parameters restrict the channels, data rates and power
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Region {
+    #[serde(rename = "EU868")]
+    Eu868,                          // Europe, 863 to 870 MHz
+    #[serde(rename = "US915")]
+    Us915,                          // North America, 902 to 928 MHz
+    #[serde(rename = "AS923")]
+    As923,                          // Asia, 915 to 928 MHz
+    #[serde(rename = "CN470")]
+    Cn470,                          // China, 470 to 510 MHz
+    #[serde(rename = "AU915")]
+    Au915,                          // Australia, 915 to 928 MHz
+    #[serde(rename = "IN865")]
+    In865,                          // India, 865 to 867 MHz
+}
+
+/// Spreading factor and bandwidth of a data rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataRate {
+    pub spreading_factor: u8,       // Spreading factor
+    pub bandwidth: u32,             // Bandwidth in Hz
+}
+
+/// Frequency range sharing a duty-cycle limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubBand {
+    pub min_frequency: u32,         // Lowest frequency of the sub-band in Hz
+    pub max_frequency: u32,         // Highest frequency of the sub-band in Hz
+    pub duty_cycle: f64,            // Largest fraction of time a device may transmit in the sub-band
+}
+
+const fn dr(spreading_factor: u8, bandwidth: u32) -> DataRate {
+    DataRate { spreading_factor, bandwidth }
+}
+
+const fn sub_band(min_frequency: u32, max_frequency: u32, duty_cycle: f64) -> SubBand {
+    SubBand { min_frequency, max_frequency, duty_cycle }
+}
+
+// Uplink data rates from DR0, as in the LoRaWAN Regional Parameters RP002-1.0.4
+const EU868_DATA_RATES: [DataRate; 7] = [
+    dr(12, 125_000), dr(11, 125_000), dr(10, 125_000), dr(9, 125_000), dr(8, 125_000), dr(7, 125_000), dr(7, 250_000),
+];
+const US915_DATA_RATES: [DataRate; 5] = [
+    dr(10, 125_000), dr(9, 125_000), dr(8, 125_000), dr(7, 125_000), dr(8, 500_000),
+];
+const AU915_DATA_RATES: [DataRate; 7] = [
+    dr(12, 125_000), dr(11, 125_000), dr(10, 125_000), dr(9, 125_000), dr(8, 125_000), dr(7, 125_000), dr(8, 500_000),
+];
+const CN470_DATA_RATES: [DataRate; 6] = [
+    dr(12, 125_000), dr(11, 125_000), dr(10, 125_000), dr(9, 125_000), dr(8, 125_000), dr(7, 125_000),
+];
+
+// Duty-cycle sub-bands of ETSI EN 300 220 and of the regions applying a similar rule
+const EU868_SUB_BANDS: [SubBand; 6] = [
+    sub_band(863_000_000, 865_000_000, 0.001),
+    sub_band(865_000_000, 868_000_000, 0.01),
+    sub_band(868_000_000, 868_600_000, 0.01),
+    sub_band(868_700_000, 869_200_000, 0.001),
+    sub_band(869_400_000, 869_650_000, 0.1),
+    sub_band(869_700_000, 870_000_000, 0.01),
+];
+const AS923_SUB_BANDS: [SubBand; 1] = [
+    sub_band(915_000_000, 928_000_000, 0.01),
+];
+
+impl Region {
+    /// Region of the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "EU868" => Some(Region::Eu868),
+            "US915" => Some(Region::Us915),
+            "AS923" => Some(Region::As923),
+            "CN470" => Some(Region::Cn470),
+            "AU915" => Some(Region::Au915),
+            "IN865" => Some(Region::In865),
+            _ => None,
+        }
+    }
+
+    /// Name of the region
+    pub fn name(&self) -> &'static str {
+        match self {
+            Region::Eu868 => "EU868",
+            Region::Us915 => "US915",
+            Region::As923 => "AS923",
+            Region::Cn470 => "CN470",
+            Region::Au915 => "AU915",
+            Region::In865 => "IN865",
+        }
+    }
+
+    /// Channels of an 8-channel gateway, the second sub-band for the regions with fixed channel plans
+    pub fn default_channels(&self) -> Vec<ChannelConfig> {
+        let (channels_125, channels_500): (Vec<u32>, Vec<u32>) = match self {
+            Region::Eu868 => (vec![
+                868_100_000, 868_300_000, 868_500_000, 867_100_000,
+                867_300_000, 867_500_000, 867_700_000, 867_900_000,
+            ], vec![]),
+            Region::Us915 => ((8..16).map(|n| 902_300_000 + 200_000 * n).collect(), vec![904_600_000]),
+            Region::Au915 => ((8..16).map(|n| 915_200_000 + 200_000 * n).collect(), vec![917_500_000]),
+            Region::As923 => (vec![
+                923_200_000, 923_400_000, 922_200_000, 922_400_000,
+                922_600_000, 922_800_000, 923_000_000, 922_000_000,
+            ], vec![]),
+            Region::Cn470 => ((0..8).map(|n| 470_300_000 + 200_000 * n).collect(), vec![]),
+            Region::In865 => (vec![865_062_500, 865_402_500, 865_985_000], vec![]),
+        };
+
+        channels_125
+            .into_iter()
+            .map(ChannelConfig::new)
+            .chain(channels_500.into_iter().map(|frequency| ChannelConfig { bandwidth: 500_000, ..ChannelConfig::new(frequency) }))
+            .collect()
+    }
+
+    /// Whether a node may transmit uplinks on the channel
+    ///
+    /// Regions with fixed channel plans only allow their grid, the others any frequency of their band
+    pub fn is_uplink_channel(&self, frequency: u32, bandwidth: u32) -> bool {
+        let on_grid = |first: u32, step: u32, count: u32| {
+            frequency >= first && (frequency - first).is_multiple_of(step) && (frequency - first) / step < count
+        };
+        match (self, bandwidth) {
+            (Region::Eu868, 125_000 | 250_000) => (863_000_000..=870_000_000).contains(&frequency),
+            (Region::As923, 125_000 | 250_000) => (915_000_000..=928_000_000).contains(&frequency),
+            (Region::In865, 125_000) => (865_000_000..=867_000_000).contains(&frequency),
+            (Region::Us915, 125_000) => on_grid(902_300_000, 200_000, 64),
+            (Region::Us915, 500_000) => on_grid(903_000_000, 1_600_000, 8),
+            (Region::Au915, 125_000) => on_grid(915_200_000, 200_000, 64),
+            (Region::Au915, 500_000) => on_grid(915_900_000, 1_600_000, 8),
+            (Region::Cn470, 125_000) => on_grid(470_300_000, 200_000, 96),
+            _ => false,
+        }
+    }
+
+    /// Uplink data rates, from DR0
+    pub fn data_rates(&self) -> &'static [DataRate] {
+        match self {
+            Region::Eu868 | Region::As923 => &EU868_DATA_RATES,
+            Region::Us915 => &US915_DATA_RATES,
+            Region::Au915 => &AU915_DATA_RATES,
+            Region::Cn470 | Region::In865 => &CN470_DATA_RATES,
+        }
+    }
+
+    /// Whether uplinks may use the spreading factor at the bandwidth
+    pub fn allows(&self, spreading_factor: u8, bandwidth: u32) -> bool {
+        self.data_rates().contains(&dr(spreading_factor, bandwidth))
+    }
+
+    /// Highest EIRP a node may transmit at in dBm
+    pub fn max_eirp_dbm(&self) -> f64 {
+        match self {
+            Region::Eu868 | Region::As923 => 16.0,
+            Region::Cn470 => 19.15,
+            Region::Us915 | Region::Au915 | Region::In865 => 30.0,
+        }
+    }
+
+    /// Frequency and data rate of the first receive window answering an uplink, with no data rate offset
+    ///
+    /// Regions with fixed channel plans answer on their downlink channels at 500 kHz, the others on the uplink channel
+    pub fn rx1(&self, frequency: u32, data_rate: DataRate) -> (u32, DataRate) {
+        let wide = |first_125: u32, first_500: u32| {
+            let channel = if data_rate.bandwidth == 500_000 {
+                64 + frequency.saturating_sub(first_500) / 1_600_000
+            } else {
+                frequency.saturating_sub(first_125) / 200_000
+            };
+            let spreading_factor = if data_rate.bandwidth == 500_000 { 7 } else { data_rate.spreading_factor };
+            (923_300_000 + 600_000 * (channel % 8), dr(spreading_factor, 500_000))
+        };
+        match self {
+            Region::Us915 => wide(902_300_000, 903_000_000),
+            Region::Au915 => wide(915_200_000, 915_900_000),
+            Region::Cn470 => (500_300_000 + 200_000 * (frequency.saturating_sub(470_300_000) / 200_000 % 48), data_rate),
+            Region::Eu868 | Region::As923 | Region::In865 => (frequency, data_rate),
+        }
+    }
+
+    /// Default frequency and data rate of the second receive window
+    pub fn rx2(&self) -> (u32, DataRate) {
+        match self {
+            Region::Eu868 => (869_525_000, dr(12, 125_000)),
+            Region::Us915 | Region::Au915 => (923_300_000, dr(12, 500_000)),
+            Region::As923 => (923_200_000, dr(10, 125_000)),
+            Region::Cn470 => (505_300_000, dr(12, 125_000)),
+            Region::In865 => (866_550_000, dr(10, 125_000)),
+        }
+    }
+
+    /// Frequency, data rate and payload length in bytes of the Class B beacon of the given beacon period
+    ///
+    /// US915 and AU915 hop over the eight downlink channels from one beacon period to the next
+    pub fn beacon(&self, period: u64) -> (u32, DataRate, u8) {
+        match self {
+            Region::Eu868 => (869_525_000, dr(9, 125_000), 17),
+            Region::Us915 | Region::Au915 => (923_300_000 + 600_000 * (period % 8) as u32, dr(12, 500_000), 23),
+            Region::As923 => (923_400_000, dr(9, 125_000), 17),
+            Region::Cn470 => (508_300_000, dr(10, 125_000), 19),
+            Region::In865 => (866_550_000, dr(8, 125_000), 19),
+        }
+    }
+
+    /// Default frequency and data rate of the Class B ping slots of a node in the given beacon period
+    ///
+    /// US915 and AU915 hop over the eight downlink channels, from a channel depending on the node
+    pub fn ping_slot(&self, period: u64, node_id: u32) -> (u32, DataRate) {
+        match self {
+            Region::Us915 | Region::Au915 => {
+                (923_300_000 + 600_000 * ((period + u64::from(node_id)) % 8) as u32, dr(12, 500_000))
+            }
+            _ => {
+                let (frequency, data_rate, _) = self.beacon(period);
+                (frequency, data_rate)
+            }
+        }
+    }
+
+    /// Sub-bands with a duty-cycle limit, empty for the regions regulating airtime otherwise
+    pub fn sub_bands(&self) -> &'static [SubBand] {
+        match self {
+            Region::Eu868 => &EU868_SUB_BANDS,
+            Region::As923 => &AS923_SUB_BANDS,
+            Region::Us915 | Region::Au915 | Region::Cn470 | Region::In865 => &[],
+        }
+    }
+}
\ No newline at end of file
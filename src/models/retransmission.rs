@@ -0,0 +1,190 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::models::rng::{stream_rng, RngStream};
+
+use chrono::Duration;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+
+/// Delay before a timed-out packet re-enters the pending queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Backoff {
+    /// The same delay before every retry
+    Fixed {
+        #[serde(default)]
+        delay_ms: f64,              // Delay before each retry
+    },
+    /// Doubling delay, `base_ms` before the first retry
+    Exponential {
+        #[serde(default = "default_base_ms")]
+        base_ms: f64,               // Delay before the first retry
+        #[serde(default = "default_max_ms")]
+        max_ms: f64,                // Largest delay
+    },
+    /// Uniformly random delay as in ALOHA
+    Random {
+        #[serde(default = "default_window_ms")]
+        max_ms: f64,                // Largest delay
+    },
+}
+
+fn default_base_ms() -> f64 {
+    1000.0
+}
+
+fn default_max_ms() -> f64 {
+    64_000.0
+}
+
+fn default_window_ms() -> f64 {
+    5000.0
+}
+
+/// Names of all backoff strategies
+pub const BACKOFF_NAMES: [&str; 3] = ["fixed", "exponential", "random"];
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::Fixed { delay_ms: 0.0 }
+    }
+}
+
+impl Backoff {
+    /// Build a backoff from its name, with the default parameters
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fixed" => Some(Backoff::default()),
+            "exponential" => Some(Backoff::Exponential { base_ms: default_base_ms(), max_ms: default_max_ms() }),
+            "random" => Some(Backoff::Random { max_ms: default_window_ms() }),
+            _ => None,
+        }
+    }
+
+    /// Check the parameters, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        let non_negative = |value: f64| value.is_finite() && value >= 0.0;
+        match self {
+            Backoff::Fixed { delay_ms } if !non_negative(*delay_ms) => {
+                Err(format!("delay_ms must not be negative, got {}", delay_ms))
+            }
+            Backoff::Exponential { base_ms, max_ms } if !non_negative(*base_ms) || !non_negative(*max_ms) => {
+                Err(format!("base_ms and max_ms must not be negative, got {} and {}", base_ms, max_ms))
+            }
+            Backoff::Exponential { base_ms, max_ms } if base_ms > max_ms => {
+                Err(format!("base_ms must not exceed max_ms, got {} and {}", base_ms, max_ms))
+            }
+            Backoff::Random { max_ms } if !non_negative(*max_ms) => {
+                Err(format!("max_ms must not be negative, got {}", max_ms))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Retry limit and backoff of the packets timing out in the pending queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetransmissionConfig {
+    pub max_retries: u32,           // Retries of a packet before it is dropped
+    pub backoff: Backoff,           // Delay before a retry
+}
+
+impl Default for RetransmissionConfig {
+    fn default() -> Self {
+        RetransmissionConfig {
+            max_retries: 3,
+            backoff: Backoff::default(),
+        }
+    }
+}
+
+/// Retransmission policy of a gateway, drawing random backoffs from its own stream
+#[derive(Debug)]
+pub struct Retransmission {
+    pub max_retries: u32,           // Retries of a packet before it is dropped
+    backoff: Backoff,               // Delay before a retry
+    rng: ChaCha8Rng,                // Backoff stream
+}
+
+impl Retransmission {
+    /// Policy of the configuration, random backoffs use the backoff stream of `seed`
+    pub fn new(config: &RetransmissionConfig, seed: u64) -> Self {
+        Retransmission {
+            max_retries: config.max_retries,
+            backoff: config.backoff.clone(),
+            rng: stream_rng(seed, RngStream::Backoff),
+        }
+    }
+
+    /// Delay before the given retry, counted from 1, of a validated backoff
+    pub fn delay(&mut self, retry: u32) -> Duration {
+        let delay_ms = match &self.backoff {
+            Backoff::Fixed { delay_ms } => *delay_ms,
+            Backoff::Exponential { base_ms, max_ms } => {
+                (base_ms * 2f64.powi(retry.saturating_sub(1).min(62) as i32)).min(*max_ms)
+            }
+            Backoff::Random { max_ms } => self.rng.gen::<f64>() * max_ms,
+        };
+        Duration::microseconds((delay_ms * 1000.0).round() as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retransmission(backoff: Backoff) -> Retransmission {
+        Retransmission::new(&RetransmissionConfig { max_retries: 3, backoff }, 1)
+    }
+
+    #[test]
+    fn fixed_backoff_waits_the_same_delay_before_every_retry() {
+        let mut retransmission = retransmission(Backoff::Fixed { delay_ms: 250.0 });
+        for retry in 1..=3 {
+            assert_eq!(retransmission.delay(retry), Duration::milliseconds(250));
+        }
+    }
+
+    #[test]
+    fn exponential_backoff_doubles_up_to_the_largest_delay() {
+        let mut retransmission = retransmission(Backoff::Exponential { base_ms: 1000.0, max_ms: 5000.0 });
+        let delays: Vec<i64> = (1..=5).map(|retry| retransmission.delay(retry).num_milliseconds()).collect();
+        assert_eq!(delays, vec![1000, 2000, 4000, 5000, 5000]);
+        assert_eq!(retransmission.delay(u32::MAX), Duration::milliseconds(5000));
+    }
+
+    #[test]
+    fn random_backoff_stays_within_the_window_and_follows_the_seed() {
+        let mut first = retransmission(Backoff::Random { max_ms: 5000.0 });
+        let mut second = retransmission(Backoff::Random { max_ms: 5000.0 });
+        let delays: Vec<Duration> = (1..=100).map(|retry| first.delay(retry)).collect();
+        assert!(delays.iter().all(|delay| *delay >= Duration::zero() && *delay <= Duration::milliseconds(5000)));
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+        assert_eq!(delays, (1..=100).map(|retry| second.delay(retry)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn exponential_backoff_must_start_below_its_largest_delay() {
+        assert!(Backoff::Exponential { base_ms: 1000.0, max_ms: 1000.0 }.validate().is_ok());
+        let error = Backoff::Exponential { base_ms: 2000.0, max_ms: 1000.0 }.validate().unwrap_err();
+        assert_eq!(error, "base_ms must not exceed max_ms, got 2000 and 1000");
+        assert!(Backoff::Fixed { delay_ms: -1.0 }.validate().is_err());
+        assert!(Backoff::Random { max_ms: f64::NAN }.validate().is_err());
+    }
+}
\ No newline at end of file
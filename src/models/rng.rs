@@ -0,0 +1,49 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Independent random number streams derived from the master seed of a run
+#[derive(Debug, Clone, Copy)]
+pub enum RngStream {
+    Traffic = 1,    // Generation of the packets
+    Allocation = 2, // Random channel allocation
+    Arrival = 3,    // Arrival times of the generated packets
+    Admission = 4,  // Early drops of the pending queue
+    Backoff = 5,    // Random retransmission delays
+    Placement = 6,  // Positions and radio settings of the nodes
+    Shadowing = 7,  // Log-normal shadowing of the links between nodes and gateways
+    Downlink = 8,   // Uplinks the application answers with a downlink
+    Confirmation = 9, // Delays and channels of the retransmissions of unacknowledged uplinks
+    ClassB = 10,    // Downlinks the application sends the Class B nodes
+    ClassC = 11,    // Unicast downlinks the application sends the Class C nodes
+}
+
+/// Generator of a stream, the same seed and stream always give the same sequence
+///
+/// Every stream shares the key derived from the master seed but uses its own ChaCha stream,
+/// so drawing more numbers from one stream never shifts the numbers of another
+pub fn stream_rng(seed: u64, stream: RngStream) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(stream as u64);
+    rng
+}
+
+/// Seed of the random streams of the gateway at `index`, the first gateway uses the master seed itself
+pub fn gateway_seed(seed: u64, index: usize) -> u64 {
+    seed ^ (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
\ No newline at end of file
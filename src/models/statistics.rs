@@ -0,0 +1,839 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::interference::ReceptionOutcome;
+
+use chrono::{DateTime, Local, Duration};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// Mean and percentiles of a set of samples
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    pub count: usize,        // Number of samples
+    pub mean: f64,           // Arithmetic mean
+    pub median: f64,         // 50th percentile
+    pub p95: f64,            // 95th percentile
+    pub p99: f64,            // 99th percentile
+    pub max: f64,            // Largest sample
+}
+
+impl Summary {
+    /// Summarise samples using nearest-rank percentiles
+    pub fn from_samples(samples: &[f64]) -> Self {
+        if samples.is_empty() {
+            return Summary::default();
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+
+        Summary {
+            count: sorted.len(),
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            median: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Samples kept to summarise exactly, the percentiles of longer runs are estimated in constant memory
+const EXACT_SAMPLES: usize = 10_000;
+
+/// Streaming collector of samples, exact up to `EXACT_SAMPLES` and estimated beyond
+#[derive(Debug, Clone)]
+pub struct Samples {
+    exact: Vec<f64>,                // Every sample while there are at most `EXACT_SAMPLES`
+    count: usize,                   // Number of samples
+    sum: f64,                       // Sum of the samples
+    max: f64,                       // Largest sample
+    quantiles: [Quantile; 3],       // Estimated median, 95th and 99th percentiles
+}
+
+impl Default for Samples {
+    fn default() -> Self {
+        Samples {
+            exact: Vec::new(),
+            count: 0,
+            sum: 0.0,
+            max: f64::NEG_INFINITY,
+            quantiles: [Quantile::new(0.5), Quantile::new(0.95), Quantile::new(0.99)],
+        }
+    }
+}
+
+impl Samples {
+    /// Add a sample
+    pub fn push(&mut self, sample: f64) {
+        self.count += 1;
+        self.sum += sample;
+        self.max = self.max.max(sample);
+        for quantile in &mut self.quantiles {
+            quantile.push(sample);
+        }
+        if self.count <= EXACT_SAMPLES {
+            self.exact.push(sample);
+        } else if !self.exact.is_empty() {
+            self.exact = Vec::new();
+        }
+    }
+
+    /// Mean and percentiles of the samples, nearest-rank while they are all kept
+    pub fn summary(&self) -> Summary {
+        if self.count <= EXACT_SAMPLES {
+            return Summary::from_samples(&self.exact);
+        }
+        let [median, p95, p99] = &self.quantiles;
+        Summary {
+            count: self.count,
+            mean: self.sum / self.count as f64,
+            median: median.value(),
+            p95: p95.value(),
+            p99: p99.value(),
+            max: self.max,
+        }
+    }
+}
+
+/// P² estimator of a quantile, Jain and Chlamtac, "The P² Algorithm for Dynamic Calculation of Quantiles", 1985
+#[derive(Debug, Clone)]
+struct Quantile {
+    heights: [f64; 5],              // Heights of the markers, the minimum, the quantile and the maximum in the middle and at the ends
+    positions: [f64; 5],            // Actual positions of the markers
+    desired: [f64; 5],              // Desired positions of the markers
+    increments: [f64; 5],           // Increase of the desired positions with each sample
+    count: usize,                   // Number of samples
+}
+
+impl Quantile {
+    /// Estimator of the quantile `p` within 0 to 1
+    fn new(p: f64) -> Self {
+        Quantile {
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+            count: 0,
+        }
+    }
+
+    /// Add a sample
+    fn push(&mut self, sample: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = sample;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(|a, b| a.total_cmp(b));
+            }
+            return;
+        }
+        self.count += 1;
+
+        // Cell the sample falls in, stretching the ends to it
+        let cell = if sample < self.heights[0] {
+            self.heights[0] = sample;
+            0
+        } else if sample >= self.heights[4] {
+            self.heights[4] = sample;
+            3
+        } else {
+            (1..5).find(|&i| sample < self.heights[i]).unwrap() - 1
+        };
+        for position in &mut self.positions[cell + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+
+        // Move the middle markers towards their desired positions, along a parabola when it stays monotonic
+        for i in 1..4 {
+            let offset = self.desired[i] - self.positions[i];
+            if (offset >= 1.0 && self.positions[i + 1] - self.positions[i] > 1.0)
+                || (offset <= -1.0 && self.positions[i - 1] - self.positions[i] < -1.0) {
+                let step = offset.signum();
+                let parabolic = self.parabolic(i, step);
+                self.heights[i] = if self.heights[i - 1] < parabolic && parabolic < self.heights[i + 1] {
+                    parabolic
+                } else {
+                    let j = if step > 0.0 { i + 1 } else { i - 1 };
+                    self.heights[i] + step * (self.heights[j] - self.heights[i]) / (self.positions[j] - self.positions[i])
+                };
+                self.positions[i] += step;
+            }
+        }
+    }
+
+    /// Height of marker `i` moved by `step` along the parabola through it and its neighbours
+    fn parabolic(&self, i: usize, step: f64) -> f64 {
+        let (h, n) = (&self.heights, &self.positions);
+        h[i] + step / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + step) * (h[i + 1] - h[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - step) * (h[i] - h[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    /// Estimated quantile, exact only once five samples were seen
+    fn value(&self) -> f64 {
+        self.heights[2]
+    }
+}
+
+/// Outcomes of the packets of one node
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeReport {
+    pub node_id: u32,            // Node the packets were sent by
+    pub sent: u64,               // Packets transmitted by the node
+    pub delivered: u64,          // Processed by a channel
+    pub collided: u64,           // Lost to collision
+    pub blocked: u64,            // Lost while the gateway was transmitting
+    pub dropped: u64,            // Dropped by the gateway
+    pub delivery_ratio: f64,     // Fraction of the sent packets that were delivered
+}
+
+/// Share of the uplinks of the network a gateway delivered
+#[derive(Debug, Clone, Serialize)]
+pub struct GatewayDelivery {
+    pub gateway_id: u32,         // Gateway concerned
+    pub heard: u64,              // Uplinks within range of the gateway
+    pub delivered: u64,          // Uplinks the gateway delivered
+    pub delivery_ratio: f64,     // Fraction of all the uplink transmissions the gateway delivered
+}
+
+/// Use of a duty-cycle sub-band by the nodes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SubBandUsage {
+    pub min_frequency: u32,      // Lowest frequency of the sub-band in Hz
+    pub max_frequency: u32,      // Highest frequency of the sub-band in Hz
+    pub duty_cycle: f64,         // Largest fraction of time a node may transmit in the sub-band
+    pub transmissions: u64,      // Uplinks transmitted in the sub-band
+    pub airtime_ms: f64,         // Time on air of these uplinks
+    pub deferred: u64,           // Uplinks transmitted after waiting for the duty cycle
+    pub blocked: u64,            // Uplinks given up because of the duty cycle
+}
+
+/// Uplinks held back by the duty cycle of the region
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DutyCycleReport {
+    pub deferred: u64,           // Uplinks transmitted after waiting for the duty cycle
+    pub blocked: u64,            // Uplinks given up because of the duty cycle
+    pub deferred_ratio: f64,     // Fraction of the uplinks in the sub-bands that were deferred
+    pub deferral_ms: Summary,    // How long the deferred uplinks waited
+    pub sub_bands: Vec<SubBandUsage>, // Use of each sub-band, empty when the duty cycle is not tracked
+}
+
+/// Uplinks of each spreading factor sent during an interval of the run
+#[derive(Debug, Clone, Serialize)]
+pub struct SfInterval {
+    pub start_ms: f64,           // Start of the interval from the first uplink
+    pub uplinks: [u64; 6],       // Uplinks sent at SF7 to SF12
+}
+
+/// Effect of the adaptive data rate of the network server
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AdrReport {
+    pub commands: u64,           // Changes of spreading factor or power the network server issued
+    pub energy_mj: f64,          // Energy the nodes drew to transmit their uplinks
+    pub fixed_energy_mj: f64,    // Energy they would have drawn at their initial spreading factor and power
+    pub energy_savings: f64,     // Fraction of the fixed energy saved
+    pub sf_distribution: Vec<SfInterval>, // Spreading factors over time, empty without ADR
+}
+
+/// Downlinks the network server sent in the receive windows of the nodes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DownlinkReport {
+    pub requested: u64,          // Uplinks the network server answered
+    pub rx1: u64,                // Downlinks sent in the first receive window
+    pub rx2: u64,                // Downlinks sent in the second receive window
+    pub late: u64,               // Missed both windows, the uplink reached the network server after they opened
+    pub gateway_busy: u64,       // Missed both windows, the gateway was already transmitting
+    pub duty_cycle: u64,         // Missed both windows, the duty cycle of the gateway forbade them
+    pub received: u64,           // Downlinks heard by their node
+    pub airtime_ms: f64,         // Time on air of the downlinks sent
+    pub gateway_duty_cycle: DutyCycleReport, // Airtime of the gateways in each sub-band
+}
+
+/// Confirmed uplinks, which their node retransmits until it hears an acknowledgement
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConfirmedReport {
+    pub frames: u64,             // Confirmed frames their node is done with
+    pub acknowledged: u64,       // Frames whose node heard an acknowledgement
+    pub unacknowledged: u64,     // Frames given up after `nb_trans` transmissions or dropped by the duty cycle
+    pub retransmissions: u64,    // Transmissions beyond the first of each frame
+    pub success_rate: f64,       // Fraction of the frames acknowledged
+    pub attempts_per_delivered: f64, // Mean transmissions of the frames the network server received
+}
+
+/// Class B beacons and the downlinks sent in the ping slots of the nodes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClassBReport {
+    pub requested: u64,          // Downlinks the application asked for
+    pub sent: u64,               // Downlinks scheduled in a ping slot
+    pub no_slot: u64,            // Downlinks no free ping slot was found for
+    pub received: u64,           // Downlinks heard by their node
+    pub delivery_ratio: f64,     // Fraction of the requested downlinks heard by their node
+    pub latency_ms: Summary,     // From the request to the end of each downlink sent
+    pub beacons: u64,            // Beacons sent, counted once per gateway
+    pub beacon_airtime_ms: f64,  // Time on air of these beacons
+    pub beacon_overhead: f64,    // Fraction of the time a gateway spends beaconing, deaf to uplinks
+    pub beacon_share: f64,       // Fraction of the airtime of the gateways spent beaconing
+}
+
+/// Unicast downlinks of the Class C nodes and the fragments of the multicast session
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClassCReport {
+    pub requested: u64,          // Unicast downlinks the application asked for
+    pub received: u64,           // Unicast downlinks heard by their node
+    pub delivery_ratio: f64,     // Fraction of the requested unicast downlinks heard by their node
+    pub latency_ms: Summary,     // From the request to the end of each unicast downlink
+    pub fragments: u32,          // Fragments of the multicast session sent
+    pub transmissions: u64,      // Transmissions of these fragments, one per gateway
+    pub session_ms: f64,         // From the start of the first fragment to the end of the last one
+    pub missed: u64,             // Fragments members in range missed while transmitting an uplink
+    pub blocked: u64,            // Uplinks lost at a gateway during the session while it was transmitting, counted per gateway
+    pub fragments_per_device: Summary, // Distinct fragments heard by each member of the group
+    pub min_fragments: u32,      // Fewest fragments heard by a member of the group
+    pub complete: u64,           // Members of the group that heard every fragment
+    pub devices: Vec<DeviceFragments>, // Fragments heard by each member of the group
+}
+
+/// Multicast fragments heard by a member of the group
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceFragments {
+    pub node_id: u32,            // Member of the group
+    pub received: u32,           // Distinct fragments it heard
+}
+
+/// End-of-run statistics of the network
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkReport {
+    pub seed: u64,                           // Master seed of the random streams of the run
+    pub uplinks: u64,                        // Uplinks transmitted by the nodes
+    pub out_of_range: u64,                   // Uplinks no gateway could hear
+    pub delivered: u64,                      // Distinct uplinks delivered by at least one gateway
+    pub duplicates: u64,                     // Further copies delivered by other gateways, discarded by the network server
+    pub delivery_ratio: f64,                 // Fraction of the uplinks delivered by at least one gateway
+    pub gateway_delivery: Vec<GatewayDelivery>, // Delivery of each gateway on its own
+    pub duty_cycle: DutyCycleReport,         // Uplinks deferred or blocked by the duty cycle
+    pub adr: AdrReport,                      // Settings changes and energy of the adaptive data rate
+    pub downlinks: DownlinkReport,           // Downlinks answering the uplinks in the receive windows
+    pub confirmed: ConfirmedReport,          // Acknowledgements and retransmissions of the confirmed uplinks
+    pub class_b: ClassBReport,               // Beacons and ping-slot downlinks of the Class B nodes
+    pub class_c: ClassCReport,               // Unicast and multicast downlinks of the Class C nodes
+    pub gateways: Vec<Report>,               // Statistics of each gateway
+    pub wall_time_ms: f64,                   // Real time the run took, including loading the packets
+    pub peak_memory_mb: Option<f64>,         // Peak resident memory of the process, where the platform reports it
+}
+
+/// End-of-run statistics of a gateway
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub gateway_id: u32,                     // Gateway the statistics are collected at
+    pub packets: u64,                        // Packets transmitted to the gateway
+    pub received: u64,                       // Received without harmful overlap
+    pub captured: u64,                       // Received despite overlapping transmissions
+    pub collided: u64,                       // Lost to collision
+    pub blocked: u64,                        // Lost while the gateway was transmitting a downlink
+    pub delivered: u64,                      // Processed by a channel
+    pub downlinks: u64,                      // Downlinks transmitted by the gateway
+    pub dropped: BTreeMap<String, u64>,      // Packets dropped by the gateway, by reason
+    pub timeouts: u64,                       // Packets timing out in the pending queue
+    pub retransmissions: u64,                // Timeouts followed by a retry, the others exhausted the retries
+    pub simulated_time_ms: f64,              // Duration of the run
+    pub throughput: f64,                     // Packets delivered per second
+    pub rssi_dbm: Summary,                   // Received power of the packets heard by the gateway
+    pub snr_db: Summary,                     // Signal-to-noise ratio of the packets heard by the gateway
+    pub queueing_delay_ms: Summary,          // From entering the pending queue to the start of service
+    pub latency_ms: Summary,                 // From the start of the transmission to the end of service
+    pub channel_busy_fraction: Vec<f64>,     // Fraction of the run each channel spent processing
+    pub channel_delivered: Vec<u64>,         // Packets processed by each channel
+    pub jain_fairness: f64,                  // Jain's fairness index of the channel busy fractions
+    pub max_pending_depth: usize,            // Largest length reached by the pending queue
+    pub nodes: Vec<NodeReport>,              // Outcomes broken down by source node
+    pub node_fairness: f64,                  // Jain's fairness index of the node delivery ratios
+}
+
+/// Jain's fairness index, 1 when all values are equal
+pub fn jain_fairness(values: &[f64]) -> f64 {
+    let sum: f64 = values.iter().sum();
+    let sum_of_squares: f64 = values.iter().map(|v| v * v).sum();
+    if sum_of_squares == 0.0 {
+        return 1.0;
+    }
+    sum * sum / (values.len() as f64 * sum_of_squares)
+}
+
+/// Peak resident set size of the process in MB, read from `/proc/self/status` on Linux
+pub fn peak_memory_mb() -> Option<f64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb: f64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb / 1024.0)
+}
+
+/// Milliseconds of a duration as a float
+fn as_ms(duration: Duration) -> f64 {
+    duration.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0
+}
+
+/// Shared collector of the measurements of a run
+#[derive(Debug, Clone)]
+pub struct Statistics {
+    inner: Arc<Mutex<Collector>>,
+}
+
+#[derive(Debug, Default)]
+struct NodeCounters {
+    sent: u64,
+    delivered: u64,
+    collided: u64,
+    blocked: u64,
+    dropped: u64,
+}
+
+#[derive(Debug, Default)]
+struct Collector {
+    node_of: HashMap<u32, u32>,                     // Source node of packets whose outcome is not known yet
+    first_arrival: HashMap<u32, DateTime<Local>>,   // Start of the first transmission of packets not yet delivered
+    entered_queue: HashMap<u32, DateTime<Local>>,   // Instant packets entered the pending queue
+    packets: u64,
+    received: u64,
+    captured: u64,
+    collided: u64,
+    blocked: u64,
+    delivered: u64,
+    downlinks: u64,
+    dropped: BTreeMap<String, u64>,
+    timeouts: u64,
+    retransmissions: u64,
+    rssi: Samples,
+    snr: Samples,
+    queueing_delays: Samples,
+    latencies: Samples,
+    channel_busy: Vec<Duration>,
+    channel_delivered: Vec<u64>,
+    max_pending_depth: usize,
+    nodes: BTreeMap<u32, NodeCounters>,
+}
+
+impl Statistics {
+    /// Collector for a gateway with the given number of channels
+    pub fn new(channel_count: usize) -> Self {
+        let collector = Collector {
+            channel_busy: vec![Duration::zero(); channel_count],
+            channel_delivered: vec![0; channel_count],
+            ..Collector::default()
+        };
+        Statistics { inner: Arc::new(Mutex::new(collector)) }
+    }
+
+    /// A packet sent by `node_id` starts its transmission
+    pub fn arrival(&self, packet_id: u32, node_id: u32, time: DateTime<Local>) {
+        let mut c = self.inner.lock().unwrap();
+        c.packets += 1;
+        c.nodes.entry(node_id).or_default().sent += 1;
+        c.node_of.insert(packet_id, node_id);
+        c.first_arrival.entry(packet_id).or_insert(time);
+    }
+
+    /// A packet is heard at the given received power and signal-to-noise ratio
+    pub fn link(&self, rssi: f64, snr: f64) {
+        let mut c = self.inner.lock().unwrap();
+        c.rssi.push(rssi);
+        c.snr.push(snr);
+    }
+
+    /// A transmission ends with the given outcome
+    pub fn reception(&self, packet_id: u32, time: DateTime<Local>, outcome: ReceptionOutcome) {
+        let mut c = self.inner.lock().unwrap();
+        match outcome {
+            ReceptionOutcome::Received => c.received += 1,
+            ReceptionOutcome::Captured => c.captured += 1,
+            ReceptionOutcome::Collided => {
+                c.collided += 1;
+                c.first_arrival.remove(&packet_id);
+                if let Some(node) = c.settle(packet_id) {
+                    node.collided += 1;
+                }
+                return;
+            }
+            ReceptionOutcome::Blocked => {
+                c.blocked += 1;
+                c.first_arrival.remove(&packet_id);
+                if let Some(node) = c.settle(packet_id) {
+                    node.blocked += 1;
+                }
+                return;
+            }
+        }
+        c.entered_queue.insert(packet_id, time);
+    }
+
+    /// The gateway transmits a downlink
+    pub fn downlink(&self) {
+        self.inner.lock().unwrap().downlinks += 1;
+    }
+
+    /// The pending queue reached the given length
+    pub fn pending_depth(&self, depth: usize) {
+        let mut c = self.inner.lock().unwrap();
+        c.max_pending_depth = c.max_pending_depth.max(depth);
+    }
+
+    /// A pending packet timed out
+    pub fn timeout(&self) {
+        self.inner.lock().unwrap().timeouts += 1;
+    }
+
+    /// A timed-out packet is retried
+    pub fn retransmission(&self) {
+        self.inner.lock().unwrap().retransmissions += 1;
+    }
+
+    /// The gateway dropped a packet for the given reason
+    pub fn dropped(&self, packet_id: u32, reason: &str) {
+        let mut c = self.inner.lock().unwrap();
+        *c.dropped.entry(reason.to_string()).or_insert(0) += 1;
+        c.first_arrival.remove(&packet_id);
+        c.entered_queue.remove(&packet_id);
+        if let Some(node) = c.settle(packet_id) {
+            node.dropped += 1;
+        }
+    }
+
+    /// A channel starts processing a packet
+    pub fn service_start(&self, packet_id: u32, time: DateTime<Local>) {
+        let mut c = self.inner.lock().unwrap();
+        let entered = c.entered_queue.remove(&packet_id).or_else(|| c.first_arrival.get(&packet_id).copied());
+        if let Some(entered) = entered {
+            c.queueing_delays.push(as_ms(time.signed_duration_since(entered)));
+        }
+    }
+
+    /// A channel finishes processing a packet it started at `start`
+    pub fn completion(&self, packet_id: u32, channel: usize, start: DateTime<Local>, end: DateTime<Local>) {
+        let mut c = self.inner.lock().unwrap();
+        c.delivered += 1;
+        c.channel_busy[channel] += end.signed_duration_since(start);
+        c.channel_delivered[channel] += 1;
+        if let Some(node) = c.settle(packet_id) {
+            node.delivered += 1;
+        }
+        if let Some(arrival) = c.first_arrival.remove(&packet_id) {
+            c.latencies.push(as_ms(end.signed_duration_since(arrival)));
+        }
+    }
+
+    /// Compute the statistics of gateway `gateway_id` over a run from `start` to `end`
+    pub fn report(&self, gateway_id: u32, start: DateTime<Local>, end: DateTime<Local>) -> Report {
+        let c = self.inner.lock().unwrap();
+        let duration = end.signed_duration_since(start);
+        let simulated_time_ms = as_ms(duration);
+
+        let channel_busy_fraction: Vec<f64> = c.channel_busy
+            .iter()
+            .map(|busy| if simulated_time_ms > 0.0 { as_ms(*busy) / simulated_time_ms } else { 0.0 })
+            .collect();
+
+        let nodes: Vec<NodeReport> = c.nodes
+            .iter()
+            .map(|(&node_id, counters)| NodeReport {
+                node_id,
+                sent: counters.sent,
+                delivered: counters.delivered,
+                collided: counters.collided,
+                blocked: counters.blocked,
+                dropped: counters.dropped,
+                delivery_ratio: ratio(counters.delivered, counters.sent),
+            })
+            .collect();
+        let delivery_ratios: Vec<f64> = nodes.iter().map(|node| node.delivery_ratio).collect();
+
+        Report {
+            gateway_id,
+            packets: c.packets,
+            received: c.received,
+            captured: c.captured,
+            collided: c.collided,
+            blocked: c.blocked,
+            downlinks: c.downlinks,
+            delivered: c.delivered,
+            dropped: c.dropped.clone(),
+            timeouts: c.timeouts,
+            retransmissions: c.retransmissions,
+            simulated_time_ms,
+            throughput: if simulated_time_ms > 0.0 { c.delivered as f64 / (simulated_time_ms / 1000.0) } else { 0.0 },
+            rssi_dbm: c.rssi.summary(),
+            snr_db: c.snr.summary(),
+            queueing_delay_ms: c.queueing_delays.summary(),
+            latency_ms: c.latencies.summary(),
+            jain_fairness: jain_fairness(&channel_busy_fraction),
+            channel_busy_fraction,
+            channel_delivered: c.channel_delivered.clone(),
+            max_pending_depth: c.max_pending_depth,
+            node_fairness: jain_fairness(&delivery_ratios),
+            nodes,
+        }
+    }
+}
+
+impl Collector {
+    /// Counters of the node that sent a packet whose outcome is now known
+    fn settle(&mut self, packet_id: u32) -> Option<&mut NodeCounters> {
+        let node_id = self.node_of.remove(&packet_id)?;
+        self.nodes.get_mut(&node_id)
+    }
+}
+
+/// Fraction of `count` over `total`, 0 when there is nothing to count
+pub fn ratio(count: u64, total: u64) -> f64 {
+    if total > 0 { count as f64 / total as f64 } else { 0.0 }
+}
+
+impl NetworkReport {
+    /// Network statistics from the counters of the network server and the report of every gateway
+    pub fn new(seed: u64, uplinks: u64, out_of_range: u64, delivered: u64, duplicates: u64,
+        gateways: Vec<Report>, wall_time: std::time::Duration) -> Self {
+        let gateway_delivery = gateways
+            .iter()
+            .map(|report| GatewayDelivery {
+                gateway_id: report.gateway_id,
+                heard: report.packets,
+                delivered: report.delivered,
+                delivery_ratio: ratio(report.delivered, uplinks),
+            })
+            .collect();
+
+        NetworkReport {
+            seed,
+            uplinks,
+            out_of_range,
+            delivered,
+            duplicates,
+            delivery_ratio: ratio(delivered, uplinks),
+            gateway_delivery,
+            duty_cycle: DutyCycleReport::default(),
+            adr: AdrReport::default(),
+            downlinks: DownlinkReport::default(),
+            confirmed: ConfirmedReport::default(),
+            class_b: ClassBReport::default(),
+            class_c: ClassCReport::default(),
+            gateways,
+            wall_time_ms: wall_time.as_secs_f64() * 1000.0,
+            peak_memory_mb: peak_memory_mb(),
+        }
+    }
+
+    /// Print the network statistics as a table, followed by the table of every gateway
+    pub fn print_table(&self) {
+        println!("Seed: {}", self.seed);
+        println!("+-------------------------------+----------------+");
+        println!("| {:<29} | {:>14} |", "Network", "Value");
+        println!("+-------------------------------+----------------+");
+        println!("| {:<29} | {:>14} |", "Uplinks", self.uplinks);
+        println!("| {:<29} | {:>14} |", "Out of range", self.out_of_range);
+        println!("| {:<29} | {:>14} |", "Delivered", self.delivered);
+        println!("| {:<29} | {:>14} |", "Duplicates", self.duplicates);
+        println!("| {:<29} | {:>13.2}% |", "Network delivery ratio", self.delivery_ratio * 100.0);
+        for gateway in &self.gateway_delivery {
+            println!("| {:<29} | {:>13.2}% |", format!("Gateway {} delivery ratio", gateway.gateway_id), gateway.delivery_ratio * 100.0);
+        }
+        if !self.duty_cycle.sub_bands.is_empty() {
+            let duty_cycle = &self.duty_cycle;
+            println!("| {:<29} | {:>14} |", "Deferred by duty cycle", duty_cycle.deferred);
+            println!("| {:<29} | {:>13.2}% |", "Deferred ratio", duty_cycle.deferred_ratio * 100.0);
+            println!("| {:<29} | {:>14.3} |", "Deferral mean (ms)", duty_cycle.deferral_ms.mean);
+            println!("| {:<29} | {:>14.3} |", "Deferral p95 (ms)", duty_cycle.deferral_ms.p95);
+            println!("| {:<29} | {:>14} |", "Blocked by duty cycle", duty_cycle.blocked);
+            for usage in duty_cycle.sub_bands.iter().filter(|usage| usage.transmissions + usage.blocked > 0) {
+                println!("| {:<29} | {:>14.3} |", format!("{:.1} MHz airtime (s)", f64::from(usage.min_frequency) / 1e6), usage.airtime_ms / 1000.0);
+            }
+        }
+        if self.downlinks.requested > 0 {
+            let downlinks = &self.downlinks;
+            println!("| {:<29} | {:>14} |", "Downlinks requested", downlinks.requested);
+            println!("| {:<29} | {:>14} |", "Sent in RX1", downlinks.rx1);
+            println!("| {:<29} | {:>14} |", "Sent in RX2", downlinks.rx2);
+            println!("| {:<29} | {:>14} |", "Missed, uplink too late", downlinks.late);
+            println!("| {:<29} | {:>14} |", "Missed, gateway busy", downlinks.gateway_busy);
+            println!("| {:<29} | {:>14} |", "Missed, gateway duty cycle", downlinks.duty_cycle);
+            println!("| {:<29} | {:>14} |", "Received by nodes", downlinks.received);
+            println!("| {:<29} | {:>14.3} |", "Downlink airtime (s)", downlinks.airtime_ms / 1000.0);
+            for usage in downlinks.gateway_duty_cycle.sub_bands.iter().filter(|usage| usage.transmissions > 0) {
+                println!("| {:<29} | {:>14.3} |", format!("{:.1} MHz gateway airtime (s)", f64::from(usage.min_frequency) / 1e6), usage.airtime_ms / 1000.0);
+            }
+        }
+        if self.confirmed.frames > 0 {
+            let confirmed = &self.confirmed;
+            println!("| {:<29} | {:>14} |", "Confirmed frames", confirmed.frames);
+            println!("| {:<29} | {:>14} |", "Acknowledged", confirmed.acknowledged);
+            println!("| {:<29} | {:>14} |", "Unacknowledged", confirmed.unacknowledged);
+            println!("| {:<29} | {:>14} |", "Node retransmissions", confirmed.retransmissions);
+            println!("| {:<29} | {:>13.2}% |", "Confirmed success rate", confirmed.success_rate * 100.0);
+            println!("| {:<29} | {:>14.3} |", "Attempts per delivered", confirmed.attempts_per_delivered);
+        }
+        if self.class_b.beacons > 0 {
+            let class_b = &self.class_b;
+            println!("| {:<29} | {:>14} |", "Class B downlinks requested", class_b.requested);
+            println!("| {:<29} | {:>14} |", "Sent in ping slots", class_b.sent);
+            println!("| {:<29} | {:>14} |", "No free ping slot", class_b.no_slot);
+            println!("| {:<29} | {:>14} |", "Received in ping slots", class_b.received);
+            println!("| {:<29} | {:>13.2}% |", "Class B delivery ratio", class_b.delivery_ratio * 100.0);
+            println!("| {:<29} | {:>14.3} |", "Downlink latency mean (ms)", class_b.latency_ms.mean);
+            println!("| {:<29} | {:>14.3} |", "Downlink latency p95 (ms)", class_b.latency_ms.p95);
+            println!("| {:<29} | {:>14} |", "Beacons", class_b.beacons);
+            println!("| {:<29} | {:>14.3} |", "Beacon airtime (s)", class_b.beacon_airtime_ms / 1000.0);
+            println!("| {:<29} | {:>13.3}% |", "Beacon time overhead", class_b.beacon_overhead * 100.0);
+            println!("| {:<29} | {:>13.2}% |", "Beacon share of gateway TX", class_b.beacon_share * 100.0);
+        }
+        if self.class_c.requested > 0 {
+            let class_c = &self.class_c;
+            println!("| {:<29} | {:>14} |", "Class C downlinks requested", class_c.requested);
+            println!("| {:<29} | {:>14} |", "Received in RXC", class_c.received);
+            println!("| {:<29} | {:>13.2}% |", "Class C delivery ratio", class_c.delivery_ratio * 100.0);
+            println!("| {:<29} | {:>14.3} |", "Downlink latency mean (ms)", class_c.latency_ms.mean);
+            println!("| {:<29} | {:>14.3} |", "Downlink latency p95 (ms)", class_c.latency_ms.p95);
+        }
+        if self.class_c.fragments > 0 {
+            let class_c = &self.class_c;
+            println!("| {:<29} | {:>14} |", "Multicast fragments", class_c.fragments);
+            println!("| {:<29} | {:>14} |", "Fragment transmissions", class_c.transmissions);
+            println!("| {:<29} | {:>14.3} |", "Multicast session (s)", class_c.session_ms / 1000.0);
+            println!("| {:<29} | {:>14} |", "Fragments missed in uplink", class_c.missed);
+            println!("| {:<29} | {:>14} |", "Uplinks lost in session", class_c.blocked);
+            println!("| {:<29} | {:>14} |", "Multicast group size", class_c.devices.len());
+            println!("| {:<29} | {:>14.3} |", "Fragments per device mean", class_c.fragments_per_device.mean);
+            println!("| {:<29} | {:>14} |", "Fragments per device min", class_c.min_fragments);
+            println!("| {:<29} | {:>14} |", "Devices with every fragment", class_c.complete);
+        }
+        if !self.adr.sf_distribution.is_empty() {
+            println!("| {:<29} | {:>14} |", "ADR commands", self.adr.commands);
+            println!("| {:<29} | {:>14.1} |", "TX energy (J)", self.adr.energy_mj / 1000.0);
+            println!("| {:<29} | {:>14.1} |", "TX energy at fixed SF (J)", self.adr.fixed_energy_mj / 1000.0);
+            println!("| {:<29} | {:>13.2}% |", "Energy savings", self.adr.energy_savings * 100.0);
+        }
+        println!("| {:<29} | {:>14.3} |", "Wall-clock time (s)", self.wall_time_ms / 1000.0);
+        match self.peak_memory_mb {
+            Some(mb) => println!("| {:<29} | {:>14.1} |", "Peak memory (MB)", mb),
+            None => println!("| {:<29} | {:>14} |", "Peak memory (MB)", "n/a"),
+        }
+        println!("+-------------------------------+----------------+");
+
+        if !self.adr.sf_distribution.is_empty() {
+            println!("Spreading factors over time");
+            println!("+------------+--------+--------+--------+--------+--------+--------+");
+            println!("| {:>10} | {:>6} | {:>6} | {:>6} | {:>6} | {:>6} | {:>6} |", "From (s)", "SF7", "SF8", "SF9", "SF10", "SF11", "SF12");
+            println!("+------------+--------+--------+--------+--------+--------+--------+");
+            for interval in &self.adr.sf_distribution {
+                let [sf7, sf8, sf9, sf10, sf11, sf12] = interval.uplinks;
+                println!("| {:>10.1} | {:>6} | {:>6} | {:>6} | {:>6} | {:>6} | {:>6} |",
+                    interval.start_ms / 1000.0, sf7, sf8, sf9, sf10, sf11, sf12);
+            }
+            println!("+------------+--------+--------+--------+--------+--------+--------+");
+        }
+
+        for report in &self.gateways {
+            report.print_table();
+        }
+    }
+}
+
+impl Report {
+    /// Print the statistics as a table
+    pub fn print_table(&self) {
+        let dropped: u64 = self.dropped.values().sum();
+
+        println!("Gateway {}", self.gateway_id);
+        println!("+-------------------------------+----------------+");
+        println!("| {:<29} | {:>14} |", "Metric", "Value");
+        println!("+-------------------------------+----------------+");
+        println!("| {:<29} | {:>14} |", "Packets", self.packets);
+        println!("| {:<29} | {:>14} |", "Received", self.received);
+        println!("| {:<29} | {:>14} |", "Captured", self.captured);
+        println!("| {:<29} | {:>14} |", "Lost to collision", self.collided);
+        println!("| {:<29} | {:>14} |", "Lost to gateway TX", self.blocked);
+        println!("| {:<29} | {:>14} |", "Delivered", self.delivered);
+        println!("| {:<29} | {:>14} |", "Downlinks sent", self.downlinks);
+        println!("| {:<29} | {:>14} |", "Dropped", dropped);
+        for (reason, count) in &self.dropped {
+            println!("| {:<29} | {:>14} |", format!("  {}", reason), count);
+        }
+        println!("| {:<29} | {:>14} |", "Timeouts", self.timeouts);
+        println!("| {:<29} | {:>14} |", "Retransmissions", self.retransmissions);
+        println!("| {:<29} | {:>14} |", "Max pending queue depth", self.max_pending_depth);
+        println!("| {:<29} | {:>14.1} |", "RSSI mean (dBm)", self.rssi_dbm.mean);
+        println!("| {:<29} | {:>14.1} |", "SNR mean (dB)", self.snr_db.mean);
+        println!("| {:<29} | {:>14.3} |", "Simulated time (ms)", self.simulated_time_ms);
+        println!("| {:<29} | {:>14.3} |", "Throughput (packets/s)", self.throughput);
+        for (name, summary) in [("Queueing delay", &self.queueing_delay_ms), ("Latency", &self.latency_ms)] {
+            println!("| {:<29} | {:>14.3} |", format!("{} mean (ms)", name), summary.mean);
+            println!("| {:<29} | {:>14.3} |", format!("{} median (ms)", name), summary.median);
+            println!("| {:<29} | {:>14.3} |", format!("{} p95 (ms)", name), summary.p95);
+            println!("| {:<29} | {:>14.3} |", format!("{} p99 (ms)", name), summary.p99);
+        }
+        for (i, fraction) in self.channel_busy_fraction.iter().enumerate() {
+            println!("| {:<29} | {:>13.2}% |", format!("Channel {} busy", i), fraction * 100.0);
+        }
+        println!("| {:<29} | {:>14.4} |", "Jain's fairness index", self.jain_fairness);
+        println!("| {:<29} | {:>14} |", "Nodes", self.nodes.len());
+        if let Some(worst) = self.nodes.iter().min_by(|a, b| a.delivery_ratio.total_cmp(&b.delivery_ratio)) {
+            println!("| {:<29} | {:>14} |", "Worst node", worst.node_id);
+            println!("| {:<29} | {:>13.2}% |", "Worst node delivery ratio", worst.delivery_ratio * 100.0);
+        }
+        println!("| {:<29} | {:>14.4} |", "Node fairness index", self.node_fairness);
+        println!("+-------------------------------+----------------+");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The integers below `count` in a scrambled order
+    fn scrambled(count: u64) -> impl Iterator<Item = f64> {
+        (0..count).map(move |i| (i * 7919 % count) as f64)
+    }
+
+    #[test]
+    fn samples_are_exact_up_to_the_limit() {
+        let mut samples = Samples::default();
+        let values: Vec<f64> = scrambled(EXACT_SAMPLES as u64).collect();
+        values.iter().for_each(|&value| samples.push(value));
+        let summary = samples.summary();
+        let exact = Summary::from_samples(&values);
+        assert_eq!((summary.count, summary.median, summary.p95, summary.p99), (exact.count, exact.median, exact.p95, exact.p99));
+    }
+
+    #[test]
+    fn percentiles_beyond_the_limit_are_estimated() {
+        let mut samples = Samples::default();
+        scrambled(100_000).for_each(|value| samples.push(value));
+        let summary = samples.summary();
+        assert_eq!(summary.count, 100_000);
+        assert_eq!(summary.mean, 49_999.5);
+        assert_eq!(summary.max, 99_999.0);
+        for (estimate, exact) in [(summary.median, 50_000.0), (summary.p95, 95_000.0), (summary.p99, 99_000.0)] {
+            assert!((estimate - exact).abs() < 1_000.0, "estimated {} for {}", estimate, exact);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,255 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::models::channel::Channel;
+use crate::models::packet::Packet;
+use crate::models::rng::{stream_rng, RngStream};
+
+use chrono::Duration;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Policy deciding which channel queue receives the next pending packet
+pub trait AllocationStrategy: Debug + Send + Sync {
+    /// Name used to select the strategy on the command line
+    fn name(&self) -> &'static str;
+
+    /// Position at which a packet joins the pending queue, at the back (FIFO) by default
+    fn pending_position(&self, pending_queue: &VecDeque<Packet>, _packet: &Packet) -> usize {
+        pending_queue.len()
+    }
+
+    /// Choose a channel that accepts the packet, `None` if all such channels are full
+    fn select_channel(&self, packet: &Packet, channels: &[Channel]) -> Option<usize>;
+}
+
+/// Names of all available strategies
+pub const STRATEGY_NAMES: [&str; 5] = ["round-robin", "random", "least-queue", "least-time-load", "sjf-time-load"];
+
+/// Build a strategy from its name, random strategies draw from the allocation stream of `seed`
+pub fn strategy_from_name(name: &str, seed: u64) -> Option<Arc<dyn AllocationStrategy>> {
+    match name {
+        "round-robin" => Some(Arc::new(RoundRobin::default())),
+        "random" => Some(Arc::new(RandomChannel::new(seed))),
+        "least-queue" => Some(Arc::new(LeastQueueLength)),
+        "least-time-load" => Some(Arc::new(LeastTimeLoad)),
+        "sjf-time-load" => Some(Arc::new(ShortestJobFirstTimeLoad)),
+        _ => None,
+    }
+}
+
+/// Total processing time of the packets waiting in each channel, sorted in ascending order
+fn channels_by_time_load(channels: &[Channel]) -> Vec<(usize, Duration)> {
+    let mut channel_time: Vec<(usize, Duration)> = channels
+        .iter()
+        .enumerate()
+        .map(|(i, channel)| (i, channel.time_load()))
+        .collect();
+
+    // Stable sort keeps the lower index first on equal load
+    channel_time.sort_by_key(|(_, total_time)| *total_time);
+    channel_time
+}
+
+/// Try the channels in turn, starting after the last allocated one
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: AtomicUsize,  // Channel to try first for the next packet
+}
+
+impl AllocationStrategy for RoundRobin {
+    fn name(&self) -> &'static str {
+        "round-robin"
+    }
+
+    fn select_channel(&self, packet: &Packet, channels: &[Channel]) -> Option<usize> {
+        let count = channels.len();
+        let start = self.next.load(Ordering::Relaxed);
+        let channel = (0..count)
+            .map(|offset| (start + offset) % count)
+            .find(|i| channels[*i].accepts(packet))?;
+        self.next.store((channel + 1) % count, Ordering::Relaxed);
+        Some(channel)
+    }
+}
+
+/// Pick a random channel among those that accept the packet
+#[derive(Debug)]
+pub struct RandomChannel {
+    rng: Mutex<ChaCha8Rng>,  // Generator of the allocation stream
+}
+
+impl RandomChannel {
+    pub fn new(seed: u64) -> Self {
+        RandomChannel { rng: Mutex::new(stream_rng(seed, RngStream::Allocation)) }
+    }
+}
+
+impl AllocationStrategy for RandomChannel {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn select_channel(&self, packet: &Packet, channels: &[Channel]) -> Option<usize> {
+        let available: Vec<usize> = (0..channels.len())
+            .filter(|i| channels[*i].accepts(packet))
+            .collect();
+        if available.is_empty() {
+            return None;
+        }
+        let index = self.rng.lock().unwrap().gen_range(0..available.len());
+        Some(available[index])
+    }
+}
+
+/// Pick the channel with the fewest waiting packets
+#[derive(Debug)]
+pub struct LeastQueueLength;
+
+impl AllocationStrategy for LeastQueueLength {
+    fn name(&self) -> &'static str {
+        "least-queue"
+    }
+
+    fn select_channel(&self, packet: &Packet, channels: &[Channel]) -> Option<usize> {
+        (0..channels.len())
+            .filter(|i| channels[*i].accepts(packet))
+            .min_by_key(|i| channels[*i].queue.len())
+    }
+}
+
+/// Pick the channel with the least total processing time waiting
+#[derive(Debug)]
+pub struct LeastTimeLoad;
+
+impl AllocationStrategy for LeastTimeLoad {
+    fn name(&self) -> &'static str {
+        "least-time-load"
+    }
+
+    fn select_channel(&self, packet: &Packet, channels: &[Channel]) -> Option<usize> {
+        channels_by_time_load(channels)
+            .into_iter()
+            .map(|(i, _)| i)
+            .find(|i| channels[*i].accepts(packet))
+    }
+}
+
+/// Serve the shortest pending packet first, on the channel with the least time load
+#[derive(Debug)]
+pub struct ShortestJobFirstTimeLoad;
+
+impl AllocationStrategy for ShortestJobFirstTimeLoad {
+    fn name(&self) -> &'static str {
+        "sjf-time-load"
+    }
+
+    fn pending_position(&self, pending_queue: &VecDeque<Packet>, packet: &Packet) -> usize {
+        // Shorter processing_time is processed first, the queue stays sorted and FIFO among equal times
+        pending_queue.partition_point(|p| p.processing_time <= packet.processing_time)
+    }
+
+    fn select_channel(&self, packet: &Packet, channels: &[Channel]) -> Option<usize> {
+        LeastTimeLoad.select_channel(packet, channels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::channel::ChannelConfig;
+    use crate::models::phy::PhyParams;
+    use chrono::Local;
+
+    const FREQUENCY: u32 = 868_100_000;
+
+    fn packet(packet_id: u32, spreading_factor: u8) -> Packet {
+        Packet::from_phy(packet_id, PhyParams { frequency: FREQUENCY, spreading_factor, ..PhyParams::default() }, Local::now())
+    }
+
+    /// Channels tuned to the packets, holding packets of the given spreading factors
+    fn channels(queues: &[&[u8]]) -> Vec<Channel> {
+        queues.iter().map(|queue| {
+            let mut channel = Channel::new(&ChannelConfig { capacity: 3, ..ChannelConfig::new(FREQUENCY) });
+            channel.queue.extend(queue.iter().map(|&sf| packet(0, sf)));
+            channel
+        }).collect()
+    }
+
+    /// Channels the strategy picks for `count` packets, leaving the channels unchanged
+    fn picks(strategy: &dyn AllocationStrategy, channels: &[Channel], count: usize) -> Vec<Option<usize>> {
+        (0..count).map(|_| strategy.select_channel(&packet(1, 7), channels)).collect()
+    }
+
+    #[test]
+    fn every_strategy_is_built_from_its_name() {
+        for name in STRATEGY_NAMES {
+            assert_eq!(strategy_from_name(name, 1).unwrap().name(), name);
+        }
+        assert!(strategy_from_name("fastest", 1).is_none());
+    }
+
+    #[test]
+    fn round_robin_takes_the_channels_in_turn_skipping_full_ones() {
+        let strategy = RoundRobin::default();
+        assert_eq!(picks(&strategy, &channels(&[&[], &[], &[]]), 4), vec![Some(0), Some(1), Some(2), Some(0)]);
+        assert_eq!(picks(&strategy, &channels(&[&[], &[7, 7, 7], &[]]), 3), vec![Some(2), Some(0), Some(2)]);
+        assert_eq!(picks(&strategy, &channels(&[&[7, 7, 7]]), 1), vec![None]);
+    }
+
+    #[test]
+    fn random_picks_only_accepting_channels_and_follows_the_seed() {
+        let channels = channels(&[&[], &[7, 7, 7], &[]]);
+        let first = picks(&RandomChannel::new(1), &channels, 50);
+        assert!(first.iter().all(|pick| matches!(pick, Some(0) | Some(2))));
+        assert!(first.contains(&Some(0)) && first.contains(&Some(2)));
+        assert_eq!(first, picks(&RandomChannel::new(1), &channels, 50));
+    }
+
+    #[test]
+    fn least_queue_picks_the_fewest_waiting_packets() {
+        assert_eq!(picks(&LeastQueueLength, &channels(&[&[7, 7], &[12], &[7, 7]]), 1), vec![Some(1)]);
+        // The lowest index wins a tie
+        assert_eq!(picks(&LeastQueueLength, &channels(&[&[7], &[12], &[]]), 1), vec![Some(2)]);
+        assert_eq!(picks(&LeastQueueLength, &channels(&[&[7], &[12]]), 1), vec![Some(0)]);
+    }
+
+    #[test]
+    fn least_time_load_picks_the_least_processing_time_waiting() {
+        // One SF12 packet outweighs two SF7 packets
+        let channels = channels(&[&[12], &[7, 7], &[7, 7, 7]]);
+        assert_eq!(picks(&LeastTimeLoad, &channels, 1), vec![Some(1)]);
+        assert_eq!(picks(&ShortestJobFirstTimeLoad, &channels, 1), vec![Some(1)]);
+        assert_eq!(picks(&LeastQueueLength, &channels, 1), vec![Some(0)]);
+    }
+
+    #[test]
+    fn shortest_job_first_keeps_the_pending_queue_sorted_and_fifo_among_equals() {
+        let mut pending_queue = VecDeque::new();
+        for (packet_id, sf) in [(1, 9), (2, 7), (3, 12), (4, 9), (5, 7)] {
+            let packet = packet(packet_id, sf);
+            let position = ShortestJobFirstTimeLoad.pending_position(&pending_queue, &packet);
+            pending_queue.insert(position, packet);
+        }
+        assert_eq!(pending_queue.iter().map(|p| p.packet_id).collect::<Vec<_>>(), vec![2, 5, 1, 4, 3]);
+        assert_eq!(RoundRobin::default().pending_position(&pending_queue, &packet(6, 7)), 5);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,119 @@
+/*
+ * Copyright (C) 2025 [Yuxuan Huang - NUAA]
+ * 
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ * 
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ * 
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// Step in the lifecycle of a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceEvent {
+    Arrival,                 // Transmission starts
+    Reception,               // Transmission ends, detail is the reception outcome
+    AllocationAttempt,       // Allocation to a channel, without channel when all are full
+    ServiceStart,            // Channel starts processing the packet
+    Completion,              // Channel finishes processing the packet
+    Timeout,                 // Waited longer than the time threshold, resent
+    Outcome,                 // Final outcome of the packet
+    Downlink,                // Gateway answers the packet with a downlink, detail is the receive window
+}
+
+impl TraceEvent {
+    /// Name of the event in the exported files
+    pub fn name(&self) -> &'static str {
+        match self {
+            TraceEvent::Arrival => "arrival",
+            TraceEvent::Reception => "reception",
+            TraceEvent::AllocationAttempt => "allocation_attempt",
+            TraceEvent::ServiceStart => "service_start",
+            TraceEvent::Completion => "completion",
+            TraceEvent::Timeout => "timeout",
+            TraceEvent::Outcome => "outcome",
+            TraceEvent::Downlink => "downlink",
+        }
+    }
+}
+
+/// One line of the trace
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceRecord {
+    pub time_ms: f64,                // Time since the start of the simulation in milliseconds
+    pub gateway: u32,                // Gateway the event happened at
+    pub packet_id: u32,              // Packet concerned
+    pub event: TraceEvent,           // Lifecycle step
+    pub channel: Option<usize>,      // Channel involved, if any
+    pub detail: Option<String>,      // Outcome or reason, if any
+}
+
+/// Shared recorder of packet lifecycle events, does nothing when disabled
+#[derive(Debug, Clone)]
+pub struct Trace {
+    inner: Option<Arc<Mutex<TraceLog>>>,
+    gateway: u32,                    // Gateway the events of this handle are recorded for
+}
+
+#[derive(Debug)]
+struct TraceLog {
+    origin: DateTime<Local>,         // Instant the times are measured from
+    records: Vec<TraceRecord>,       // Recorded events in order
+}
+
+impl Trace {
+    /// Recorder that keeps nothing
+    pub fn disabled() -> Self {
+        Trace { inner: None, gateway: 0 }
+    }
+
+    /// Recorder measuring times from the given instant
+    pub fn new(origin: DateTime<Local>) -> Self {
+        Trace {
+            inner: Some(Arc::new(Mutex::new(TraceLog { origin, records: Vec::new() }))),
+            gateway: 0,
+        }
+    }
+
+    /// Handle recording into the same trace for the given gateway
+    pub fn for_gateway(&self, gateway: u32) -> Trace {
+        Trace { inner: self.inner.clone(), gateway }
+    }
+
+    /// Record an event of a packet
+    pub fn record(&self, time: DateTime<Local>, packet_id: u32, event: TraceEvent, channel: Option<usize>, detail: Option<&str>) {
+        if let Some(inner) = &self.inner {
+            let mut log = inner.lock().unwrap();
+            let elapsed = time.signed_duration_since(log.origin);
+            let time_ms = elapsed.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0;
+            log.records.push(TraceRecord {
+                time_ms,
+                gateway: self.gateway,
+                packet_id,
+                event,
+                channel,
+                detail: detail.map(String::from),
+            });
+        }
+    }
+
+    /// Copy of the recorded events, empty when disabled
+    pub fn records(&self) -> Vec<TraceRecord> {
+        match &self.inner {
+            Some(inner) => inner.lock().unwrap().records.clone(),
+            None => Vec::new(),
+        }
+    }
+}
\ No newline at end of file